@@ -9,6 +9,12 @@ pub const ROUND_SEED: &str = "stage";
 #[constant]
 pub const USER_CONTRIBUTION_SEED: &str = "user_contribution";
 
+#[constant]
+pub const TOKEN_VAULT_SEED: &str = "token_vault";
+
+#[constant]
+pub const SOL_VAULT_SEED: &str = "sol_vault";
+
 #[constant]
 #[cfg(not(feature = "devnet"))]
 pub const USDC_MINT: Pubkey =
@@ -25,16 +31,60 @@ pub const USDT_MINT: Pubkey =
 pub const USDT_MINT: Pubkey =
     Pubkey::from_str_const("7JUTQ4o61GTP8yvUat3vzuWcrBzL4QwCfsqRU3ve3QCV");
 
+#[constant]
+pub const ACCEPTED_MINT_SEED: &str = "accepted_mint";
+
+#[constant]
+pub const TOKEN_CONTRIBUTION_SEED: &str = "token_contribution";
+
+#[constant]
+pub const ORACLE_CONFIG_SEED: &str = "oracle_config";
+
+#[constant]
+pub const DEPLOYER_SEED: &str = "deployer";
+
+#[constant]
+pub const PENDING_CHANGE_SEED: &str = "pending_change";
+
+// Defaults of the OracleConfig created with the presale
 #[constant]
 pub const SOL_USD_PRICE_FEED_ACCOUNT: Pubkey =
     Pubkey::from_str_const("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE");
 
 #[constant]
-pub const AUTHORITY: Pubkey = Pubkey::from_str_const("4A6wmefCuivUh3aJbriWef5MbBx5TwkEd3wnLrPXvjbL");
+pub const SOL_USD_FEED_ID: &str =
+    "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
 
+#[constant]
+#[cfg(not(feature = "devnet"))]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+#[cfg(feature = "devnet")]
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+
+pub const DEFAULT_MAX_PRICE_DEVIATION_BPS: u16 = 100; // 1 %
+
+#[cfg(not(feature = "devnet"))]
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 60;
+#[cfg(feature = "devnet")]
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 3000000000;
+
+#[constant]
+pub const PAYMENT_METHOD_SOL: u8 = 1 << 0;
+
+#[constant]
+pub const PAYMENT_METHOD_USDC: u8 = 1 << 1;
+
+#[constant]
+pub const PAYMENT_METHOD_USDT: u8 = 1 << 2;
+
+pub const ALL_PAYMENT_METHODS: u8 = PAYMENT_METHOD_SOL | PAYMENT_METHOD_USDC | PAYMENT_METHOD_USDT;
 
 pub const MAX_STAGES: usize = 10;
 
+pub const MAX_PRICE_POINTS: usize = 4;
+
 pub const MAX_CONTRIBUTION_USD_PER_USER: u64 = 50_000 * (10_u64.pow(USDC_DECIMALS as u32));
 
 pub const BASIS_POINTS: usize = 10_000; // 100 %
@@ -43,6 +93,17 @@ pub const MAX_BASIS_POINTS: usize = 1_000; // 10 %
 
 pub const START_ROUND_ID: u8 = 1;
 
+pub const MAX_TIMELOCK_DELAY: i64 = 30 * 24 * 60 * 60; // 30 days
+
+// Soft cap changes of escrowed sales wait at least this long, even without a timelock delay
+pub const MIN_ESCROW_SOFT_CAP_DELAY: i64 = 24 * 60 * 60; // 1 day
+
+pub const DEFAULT_MAX_PRICE_CONF_BPS: u16 = 200; // 2 %
+
+// Pyth exponents accepted for the SOL/USD price, prices are converted with 10^(12 + exponent)
+pub const MIN_PRICE_EXPONENT: i32 = -12;
+pub const MAX_PRICE_EXPONENT: i32 = 0;
+
 pub const MAX_TOKEN_CAP: u64 = 330_000_000 * (10_u64.pow(LAVA_DECIMALS as u32));
 
 pub const USDC_DECIMALS: u8 = 6;
@@ -38,4 +38,68 @@ pub enum ErrorCode {
     PresaleNotPaused,
     #[msg("Round is not active")]
     RoundNotActive,
+    #[msg("Token mint already set")]
+    TokenMintAlreadySet,
+    #[msg("Invalid token mint")]
+    InvalidTokenMint,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("TGE timestamp has not been set")]
+    TgeNotSet,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Vesting has already started")]
+    VestingAlreadyStarted,
+    #[msg("Missing round account")]
+    MissingRoundAccount,
+    #[msg("Payment method is disabled")]
+    PaymentMethodDisabled,
+    #[msg("Round has already started")]
+    RoundAlreadyStarted,
+    #[msg("Token amount is below min_tokens_out")]
+    InsufficientTokensOut,
+    #[msg("Payment exceeds max_payment")]
+    MaxPaymentExceeded,
+    #[msg("Oracle price is not positive")]
+    NonPositiveOraclePrice,
+    #[msg("Oracle price exponent out of range")]
+    InvalidOracleExponent,
+    #[msg("Oracle confidence interval too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Invalid oracle configuration")]
+    InvalidOracleConfig,
+    #[msg("No fresh oracle price available")]
+    OraclePriceStale,
+    #[msg("Oracle prices deviate too much")]
+    OraclePriceDeviation,
+    #[msg("Invalid oracle account")]
+    InvalidOracleAccount,
+    #[msg("Invalid accepted mint configuration")]
+    InvalidAcceptedMint,
+    #[msg("Escrow account is required while escrow is enabled")]
+    MissingEscrowAccount,
+    #[msg("Mint has an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Quote does not match the purchase")]
+    InvalidQuote,
+    #[msg("Quote has expired")]
+    QuoteExpired,
+    #[msg("Quote nonce has already been used")]
+    QuoteNonceUsed,
+    #[msg("Quote is not signed by the cosigner")]
+    InvalidQuoteSignature,
+    #[msg("Purchase price exceeds the quoted price")]
+    QuotePriceExceeded,
+    #[msg("Change must be queued through the timelock")]
+    TimelockActive,
+    #[msg("Queued change is not effective yet")]
+    TimelockNotElapsed,
+    #[msg("Invalid timelock delay")]
+    InvalidTimelockDelay,
+    #[msg("Account required by the queued change is missing")]
+    MissingChangeAccount,
+    #[msg("User token account is required to refund its contribution")]
+    MissingUserTokenAccount,
+    #[msg("Soft cap can no longer be changed")]
+    SoftCapLocked,
 }
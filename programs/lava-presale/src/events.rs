@@ -1,11 +1,23 @@
 use anchor_lang::prelude::*;
 
-use crate::ReferralData;
+use crate::purchase::RoundFill;
+use crate::{ConfigChange, OracleSource, PythFeed, ReferralData, Role};
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub enum Asset {
     SOL,
     USD,
+    Token { mint: Pubkey },
+}
+
+/// Oracle price a SOL purchase was converted at, in Pyth's representation
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub source: OracleSource,
 }
 
 #[event]
@@ -17,4 +29,196 @@ pub struct Contributed {
     pub stage_id: u8,
     pub referral: Option<ReferralData>,
     pub asset: Asset,
+    pub fills: Vec<RoundFill>,
+    pub amount_paid: u64, // Lamports for SOL, base units of the stablecoin for USD
+    pub oracle_price: Option<OraclePrice>,
+}
+
+#[event]
+pub struct TokensClaimed {
+    pub user: Pubkey,
+    pub amount_tokens: u64,
+    pub total_claimed_tokens: u64,
+}
+
+#[event]
+pub struct TgeTimestampUpdated {
+    pub tge_timestamp: i64,
+}
+
+#[event]
+pub struct Refunded {
+    pub user: Pubkey,
+    pub sol_lamports: u64,
+    pub usdc_amount: u64,
+    pub usdt_amount: u64,
+}
+
+#[event]
+pub struct ProceedsWithdrawn {
+    pub treasury: Pubkey,
+    pub sol_lamports: u64,
+    pub usdc_amount: u64,
+    pub usdt_amount: u64,
+}
+
+#[event]
+pub struct PresalePaused {
+    pub pauser: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PresaleUnpaused {
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentMethodsUpdated {
+    pub enabled_payment_methods: u8,
+}
+
+#[event]
+pub struct OracleConfigUpdated {
+    pub feed_id: [u8; 32],
+    pub price_account: Pubkey,
+    pub max_staleness: u64,
+    pub max_price_conf_bps: u16,
+    pub conservative_pricing: bool,
+    pub primary_source: OracleSource,
+    pub switchboard_feed: Pubkey,
+    pub max_deviation_bps: u16,
+}
+
+#[event]
+pub struct AcceptedMintUpdated {
+    pub mint: Pubkey,
+    pub price_feed: Option<PythFeed>,
+    pub stable: bool,
+    pub treasury_ata: Pubkey,
+    pub enabled: bool,
+    pub allow_permanent_delegate: bool,
+}
+
+#[event]
+pub struct RoundAdvanced {
+    pub from_round_id: u8,
+    pub to_round_id: u8,
+    pub sold_out: bool,
+}
+
+#[event]
+pub struct RoundsConfigured {
+    pub first_round_id: u8,
+    pub last_round_id: u8,
+}
+
+#[event]
+pub struct RoundUpdated {
+    pub round_id: u8,
+    pub token_price_usd: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub token_supply: u64,
+}
+
+#[event]
+pub struct RoundExtended {
+    pub round_id: u8,
+    pub previous_end_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct RoundCancelled {
+    pub round_id: u8,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub previous_authority: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub previous_treasury: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub role: Role,
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct PresaleInitialized {
+    pub presale_id: u64,
+    pub presale_config: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct DeployerAdded {
+    pub deployer: Pubkey,
+}
+
+#[event]
+pub struct DeployerRemoved {
+    pub deployer: Pubkey,
+}
+
+#[event]
+pub struct ChangeQueued {
+    pub change_id: u64,
+    pub change: ConfigChange,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct ChangeExecuted {
+    pub change_id: u64,
+}
+
+#[event]
+pub struct ChangeCancelled {
+    pub change_id: u64,
+}
+
+#[event]
+pub struct SoftCapUpdated {
+    pub soft_cap_usd: u64,
+}
+
+#[event]
+pub struct TimelockDelayUpdated {
+    pub timelock_delay: i64,
+}
+
+#[event]
+pub struct TokenRefunded {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokenProceedsWithdrawn {
+    pub treasury_ata: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
 }
@@ -0,0 +1,32 @@
+use crate::error::ErrorCode;
+use crate::events::AuthorityTransferred;
+use crate::{PresaleConfig, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = pending_authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    let previous_authority = presale_config.authority;
+
+    presale_config.authority = presale_config.pending_authority;
+    presale_config.pending_authority = Pubkey::default();
+
+    emit!(AuthorityTransferred {
+        previous_authority,
+        authority: presale_config.authority,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::error::ErrorCode;
+use crate::events::AcceptedMintUpdated;
+use crate::payment_mint::check_extensions;
+use crate::{AcceptedMint, AcceptedMintData, PresaleConfig, ACCEPTED_MINT_SEED, PRESALE_SEED};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct AddAcceptedMint<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Treasury wallet that receives the payments
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        has_one = treasury @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = AcceptedMint::DISCRIMINATOR.len() + AcceptedMint::INIT_SPACE,
+        seeds = [ACCEPTED_MINT_SEED.as_bytes(), presale_config.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Holds the payments while escrow is enabled
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddAcceptedMint>, data: AcceptedMintData) -> Result<()> {
+    data.validate()?;
+    check_extensions(
+        &ctx.accounts.mint.to_account_info(),
+        data.allow_permanent_delegate,
+    )?;
+    // Under the timelock the mint is registered disabled and enabled through queue_change
+    require!(
+        !ctx.accounts.presale_config.is_timelocked() || !data.enabled,
+        ErrorCode::TimelockActive
+    );
+
+    ctx.accounts.accepted_mint.set_inner(AcceptedMint {
+        mint: ctx.accounts.mint.key(),
+        decimals: ctx.accounts.mint.decimals,
+        price_feed: data.price_feed,
+        stable: data.stable,
+        treasury_ata: ctx.accounts.treasury_ata.key(),
+        enabled: data.enabled,
+        allow_permanent_delegate: data.allow_permanent_delegate,
+        bump: ctx.bumps.accepted_mint,
+    });
+
+    emit!(AcceptedMintUpdated {
+        mint: ctx.accounts.mint.key(),
+        price_feed: data.price_feed,
+        stable: data.stable,
+        treasury_ata: ctx.accounts.treasury_ata.key(),
+        enabled: data.enabled,
+        allow_permanent_delegate: data.allow_permanent_delegate,
+    });
+
+    Ok(())
+}
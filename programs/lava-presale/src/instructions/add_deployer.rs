@@ -0,0 +1,43 @@
+use crate::error::ErrorCode;
+use crate::events::DeployerAdded;
+use crate::{Deployer, DEPLOYER_SEED};
+use anchor_lang::prelude::*;
+
+/// Allowlists `deployer` to initialize presales, managed by the program's upgrade authority
+#[derive(Accounts)]
+#[instruction(deployer: Pubkey)]
+pub struct AddDeployer<'info> {
+    #[account(mut)]
+    pub upgrade_authority: Signer<'info>,
+
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = ProgramData::owner(),
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(
+        init,
+        payer = upgrade_authority,
+        space = Deployer::DISCRIMINATOR.len() + Deployer::INIT_SPACE,
+        seeds = [DEPLOYER_SEED.as_bytes(), deployer.as_ref()],
+        bump
+    )]
+    pub deployer_entry: Account<'info, Deployer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddDeployer>, deployer: Pubkey) -> Result<()> {
+    ctx.accounts.deployer_entry.set_inner(Deployer {
+        deployer,
+        bump: ctx.bumps.deployer_entry,
+    });
+
+    emit!(DeployerAdded { deployer });
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::error::ErrorCode;
+use crate::purchase;
+use crate::{PresaleConfig, Round, PRESALE_SEED, ROUND_SEED};
+use anchor_lang::prelude::*;
+
+/// Permissionless crank moving `current_round` past a round that sold out or ended
+/// without any purchase triggering the advance.
+#[derive(Accounts)]
+pub struct AdvanceRound<'info> {
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.current_round.to_le_bytes().as_ref()
+        ],
+        bump = active_round.bump
+    )]
+    pub active_round: Account<'info, Round>,
+
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            (presale_config.current_round + 1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Account<'info, Round>,
+}
+
+pub fn handler(ctx: Context<AdvanceRound>) -> Result<()> {
+    require!(
+        !ctx.accounts.presale_config.finalized,
+        ErrorCode::PresaleEnded
+    );
+
+    purchase::advance_round(
+        &mut ctx.accounts.presale_config,
+        &ctx.accounts.active_round,
+        &mut ctx.accounts.next_round,
+        Clock::get()?.unix_timestamp,
+    )
+}
@@ -1,19 +1,26 @@
 use crate::error::ErrorCode;
-use crate::events::{Asset, Contributed};
+use crate::events::{Asset, Contributed, OraclePrice};
+use crate::oracle::{amount_for_usd, checked_price, sol_usd_price, usd_for_amount};
+use crate::purchase::{allocate, max_tokens_for_budget, Order};
+use crate::quote::{check_price, verify, PurchaseQuote};
 use crate::{
-    PresaleConfig, ReferralData, Round, UserContribution, BASIS_POINTS, MAX_BASIS_POINTS,
-    MAX_CONTRIBUTION_USD_PER_USER, MAX_TOKEN_CAP, PRESALE_SEED, ROUND_SEED, SOL_DECIMALS,
-    SOL_USD_PRICE_FEED_ACCOUNT, USDC_DECIMALS, USER_CONTRIBUTION_SEED,
+    OracleConfig, PresaleConfig, ReferralData, Round, SolVault, UserContribution, MAX_BASIS_POINTS,
+    MAX_CONTRIBUTION_USD_PER_USER, MAX_TOKEN_CAP, ORACLE_CONFIG_SEED, PAYMENT_METHOD_SOL,
+    PRESALE_SEED, ROUND_SEED, SOL_DECIMALS, SOL_VAULT_SEED, SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+    USDC_DECIMALS, USER_CONTRIBUTION_SEED,
 };
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
 use anchor_lang::system_program::{transfer, Transfer};
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 #[derive(Accounts)]
 pub struct BuyWithSol<'info> {
-    pub authority: Signer<'info>,
+    /// Required unless the purchase carries a quote signed by the cosigner
+    #[account(address = presale_config.cosigner @ ErrorCode::Unauthorized)]
+    pub cosigner: Option<Signer<'info>>,
 
-    /// CHECK: Treasury wallet that receives funds
+    /// CHECK: Treasury wallet that receives funds when escrow is disabled
     #[account(mut)]
     pub treasury: UncheckedAccount<'info>,
 
@@ -23,142 +30,226 @@ pub struct BuyWithSol<'info> {
     #[account(
         mut,
         has_one = treasury @ ErrorCode::Unauthorized,
-        has_one = authority @ ErrorCode::Unauthorized,
-        seeds = [PRESALE_SEED.as_bytes()],
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump = presale_config.bump
     )]
     pub presale_config: Account<'info, PresaleConfig>,
 
     #[account(
-        seeds = [ROUND_SEED.as_bytes(), presale_config.current_round.to_le_bytes().as_ref()],
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.current_round.to_le_bytes().as_ref()
+        ],
         bump = active_round.bump
     )]
     pub active_round: Account<'info, Round>,
 
+    /// Next scheduled round, required once the active round is sold out or has ended
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            (presale_config.current_round + 1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Option<Account<'info, Round>>,
+
     #[account(
         init_if_needed,
         payer = user,
         space = UserContribution::DISCRIMINATOR.len() + UserContribution::INIT_SPACE,
-        seeds = [USER_CONTRIBUTION_SEED.as_bytes(), user.key().as_ref()],
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
         bump
     )]
     pub user_contribution: Account<'info, UserContribution>,
 
-    #[account(address = SOL_USD_PRICE_FEED_ACCOUNT)]
-    pub price_update: Account<'info, PriceUpdateV2>,
+    #[account(
+        mut,
+        seeds = [SOL_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = sol_vault.bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+
+    #[account(
+        seeds = [ORACLE_CONFIG_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// Pyth price, optional when the Switchboard feed is fresh
+    #[account(address = oracle_config.price_account @ ErrorCode::InvalidOracleConfig)]
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+
+    /// CHECK: Switchboard On-Demand pull feed, parsed by the oracle module. Optional when the Pyth
+    /// price is fresh
+    #[account(
+        address = oracle_config.switchboard_feed @ ErrorCode::InvalidOracleConfig,
+        owner = SWITCHBOARD_ON_DEMAND_PROGRAM_ID @ ErrorCode::InvalidOracleAccount
+    )]
+    pub switchboard_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Instructions sysvar, required with a quote to find its ed25519 signature check
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
 
     pub system_program: Program<'info, System>,
 }
 
 pub fn handler(
     ctx: Context<BuyWithSol>,
-    token_amount: u64,
+    order: Order,
     referral: Option<ReferralData>,
+    quote: Option<PurchaseQuote>,
 ) -> Result<()> {
     require!(
         !ctx.accounts.presale_config.finalized,
         ErrorCode::PresaleEnded
     );
+    require!(
+        !ctx.accounts.presale_config.paused,
+        ErrorCode::PresalePaused
+    );
+    require!(
+        ctx.accounts
+            .presale_config
+            .is_payment_method_enabled(PAYMENT_METHOD_SOL),
+        ErrorCode::PaymentMethodDisabled
+    );
     require_eq!(
         ctx.accounts.presale_config.current_round,
         ctx.accounts.active_round.round_id,
         ErrorCode::InvalidRoundConfig
     );
 
-    require!(
-        ctx.accounts.active_round.is_active(),
-        ErrorCode::RoundNotActive
-    );
+    let oracle_config = &ctx.accounts.oracle_config;
+    let switchboard_data = match &ctx.accounts.switchboard_feed {
+        Some(switchboard_feed) => Some(switchboard_feed.try_borrow_data()?),
+        None => None,
+    };
+    let (price_data, price_source) = sol_usd_price(
+        oracle_config,
+        ctx.accounts.price_update.as_deref(),
+        switchboard_data.as_deref().map(|data| &data[..]),
+        Clock::get()?.unix_timestamp,
+    )?;
+    drop(switchboard_data);
+    let sol_price_usd = checked_price(&price_data, oracle_config)?;
 
-    require_gt!(token_amount, 0);
-    let price_update = &mut ctx.accounts.price_update;
-    // get_price_no_older_than will fail if the price update is more than 30 seconds old
-    #[cfg(feature = "devnet")]
-    let maximum_age: u64 = 3000000000;
-    #[cfg(not(feature = "devnet"))]
-    let maximum_age: u64 = 60;
-    // get_price_no_older_than will fail if the price update is for a different price feed.
-    let feed_id: [u8; 32] =
-        get_feed_id_from_hex("0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d")?; // SOL/USD feed
-
-    let price_data = price_update.get_price_no_older_than(&Clock::get()?, maximum_age, &feed_id)?;
-
-    if ctx.accounts.user_contribution.total_contributed_usd == 0 {
-        ctx.accounts.user_contribution.set_inner(UserContribution {
-            user: ctx.accounts.user.key(),
-            total_contributed_usd: 0,
-            total_tokens_purchased: 0,
-            bump: ctx.bumps.user_contribution,
-        });
+    if ctx.accounts.user_contribution.user == Pubkey::default() {
+        ctx.accounts
+            .user_contribution
+            .set_inner(UserContribution::new(
+                ctx.accounts.user.key(),
+                ctx.bumps.user_contribution,
+            ));
     }
 
-    // Calculate LAVA token price in SOL
-    // token_amount has 6 decimals (actual_tokens * 10^6)
-    // token_price_usd has 6 decimals (price per 1 token * 10^6)
-    // price_data.price has 8 decimals but negative exponent (SOL price in USD * 10^8)
-
-    let sol_price_usd = price_data.price as u128;
-    let token_price_usd = ctx.accounts.active_round.token_price_usd as u128;
-
-    // Calculate total cost: (token_amount * token_price_usd) / sol_price_usd
-    //
-    // Formula:
-    //       token_amount * token_price_usd * 10^12
-    // -------------------------------------------------------
-    //   sol_price_usd * 10^|exponent| * 10^(12 - |exponent|)
-    let total_cost_usd = (token_amount as u128)
-        .checked_mul(token_price_usd)
-        .ok_or(ErrorCode::ArithmeticOverflow)?; // USD cost * 10^12
-
-    let convert_exponent: i32 = USDC_DECIMALS as i32 * 2 + price_data.exponent;
-    let convert_n = 10u128.pow(convert_exponent as u32);
-
-    msg!(
-        "total_cost_usd: {}, sol_price_usd: {}, convert_n: {}",
-        total_cost_usd,
-        sol_price_usd,
-        convert_n
-    );
-    let total_sol_lamports = (total_cost_usd as u128)
-        .checked_mul(u64::MAX as u128)
-        .ok_or(ErrorCode::ArithmeticOverflow)?
-        .checked_div(
-            sol_price_usd
-                .checked_mul(convert_n) // 10^4
-                .ok_or(ErrorCode::ArithmeticOverflow)?,
-        )
-        .ok_or(ErrorCode::ArithmeticOverflow)?
-        .checked_mul(10u128.pow(SOL_DECIMALS as u32))
-        .ok_or(ErrorCode::ArithmeticOverflow)?
-        .checked_div(u64::MAX as u128)
-        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
-
-    require_gt!(total_sol_lamports, 0);
-
-    let bonus_tokens = match &referral {
+    let bonus_bps = match &referral {
         Some(ref referral_data) => {
             require_gte!(MAX_BASIS_POINTS as u16, referral_data.bonus_percent);
-            token_amount
-                .checked_mul(referral_data.bonus_percent as u64)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(BASIS_POINTS as u64)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+            referral_data.bonus_percent
         }
         None => 0,
     };
 
-    msg!(
-        "Calculated price: {} LAVA tokens ({} with decimals) cost {} lamports (SOL price: ${}, Token price: ${})",
-        token_amount as f64 / 1_000_000.0, // Actual token amount
-        token_amount, // Raw amount with 6 decimals
-        total_sol_lamports,
-        sol_price_usd as f64 / 100_000_000.0, // Convert from 8 decimals
-        token_price_usd as f64 / 1_000_000.0  // Convert from 6 decimals
-    );
+    let now = Clock::get()?.unix_timestamp;
+    let (token_amount, payment_lamports, max_payment) = match order {
+        Order::ExactOut {
+            token_amount,
+            max_payment,
+        } => (token_amount, None, max_payment),
+        Order::ExactIn {
+            payment_amount,
+            min_tokens_out,
+        } => {
+            let token_amount = max_tokens_for_budget(
+                &ctx.accounts.presale_config,
+                &ctx.accounts.active_round,
+                ctx.accounts.next_round.as_deref(),
+                usd_for_amount(
+                    payment_amount,
+                    sol_price_usd,
+                    price_data.exponent,
+                    SOL_DECIMALS,
+                )?,
+                bonus_bps,
+                now,
+            )?;
+            require_gte!(
+                token_amount,
+                min_tokens_out,
+                ErrorCode::InsufficientTokensOut
+            );
+            (token_amount, Some(payment_amount), None)
+        }
+    };
+    require_gt!(token_amount, 0);
+
+    match &quote {
+        Some(quote) => verify(
+            quote,
+            ctx.accounts.presale_config.key(),
+            ctx.accounts.presale_config.cosigner,
+            token_amount,
+            &mut ctx.accounts.user_contribution,
+            ctx.accounts
+                .instructions_sysvar
+                .as_ref()
+                .ok_or(ErrorCode::InvalidQuoteSignature)?,
+            now,
+        )?,
+        None => require!(ctx.accounts.cosigner.is_some(), ErrorCode::Unauthorized),
+    }
+
+    let allocation = allocate(
+        &mut ctx.accounts.presale_config,
+        &mut ctx.accounts.active_round,
+        ctx.accounts.next_round.as_deref_mut(),
+        token_amount,
+        bonus_bps,
+        now,
+    )?;
+    let bonus_tokens = allocation.bonus_tokens;
+    if let Some(quote) = &quote {
+        check_price(quote, allocation.total_cost_usd, token_amount)?;
+    }
+    let total_cost_usd = allocation.total_cost_usd; // USD cost * 10^12
+
+    // Exact-in purchases spend the whole payment, the remainder is worth less than one token unit
+    let total_sol_lamports = match payment_lamports {
+        Some(payment_lamports) => payment_lamports,
+        None => amount_for_usd(
+            total_cost_usd,
+            sol_price_usd,
+            price_data.exponent,
+            SOL_DECIMALS,
+        )?,
+    };
+
+    require_gt!(total_sol_lamports, 0);
+    if let Some(max_payment) = max_payment {
+        require_gte!(
+            max_payment,
+            total_sol_lamports,
+            ErrorCode::MaxPaymentExceeded
+        );
+    }
 
     let transfer_accounts = Transfer {
         from: ctx.accounts.user.to_account_info(),
-        to: ctx.accounts.treasury.to_account_info(),
+        to: if ctx.accounts.presale_config.escrow_enabled {
+            ctx.accounts.sol_vault.to_account_info()
+        } else {
+            ctx.accounts.treasury.to_account_info()
+        },
     };
 
     transfer(
@@ -170,17 +261,24 @@ pub fn handler(
     )?;
 
     let contributed_amount_usd = total_cost_usd
-        .checked_div(10u128.pow(6 as u32))
+        .checked_div(10u128.pow(USDC_DECIMALS as u32))
         .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
 
     let token_amount_total = token_amount + bonus_tokens;
-    ctx.accounts.user_contribution.total_contributed_usd += contributed_amount_usd;
-    ctx.accounts.user_contribution.total_tokens_purchased += token_amount + bonus_tokens;
+    let user_contribution = &mut ctx.accounts.user_contribution;
+    user_contribution.total_contributed_usd += contributed_amount_usd;
+    user_contribution.contributed_sol_lamports += total_sol_lamports;
+    user_contribution.total_tokens_purchased += token_amount_total;
+    for fill in allocation.fills.iter() {
+        user_contribution.tokens_purchased_per_round[(fill.round_id - 1) as usize] +=
+            fill.amount_tokens + fill.amount_referral_bonus_tokens;
+    }
     ctx.accounts.presale_config.total_allocated_tokens += token_amount_total;
+    ctx.accounts.presale_config.total_raised_usd += contributed_amount_usd;
 
     require_gte!(
         MAX_CONTRIBUTION_USD_PER_USER,
-        ctx.accounts.user_contribution.total_contributed_usd,
+        user_contribution.total_contributed_usd,
         ErrorCode::ExceedsMaxContribution
     );
 
@@ -192,12 +290,21 @@ pub fn handler(
 
     emit!(Contributed {
         contributor: ctx.accounts.user.key(),
-        stage_id: ctx.accounts.active_round.round_id,
+        stage_id: allocation.first_round_id(),
         amount_tokens: token_amount,
         amount_referral_bonus_tokens: bonus_tokens,
         contributed_amount_usd,
         referral,
-        asset: Asset::SOL
+        asset: Asset::SOL,
+        fills: allocation.fills,
+        amount_paid: total_sol_lamports,
+        oracle_price: Some(OraclePrice {
+            price: price_data.price,
+            conf: price_data.conf,
+            exponent: price_data.exponent,
+            publish_time: price_data.publish_time,
+            source: price_source,
+        }),
     });
 
     Ok(())
@@ -0,0 +1,399 @@
+use crate::error::ErrorCode;
+use crate::events::{Asset, Contributed, OraclePrice};
+use crate::oracle::{amount_for_usd, checked_price, usd_for_amount};
+use crate::payment_mint::{check_extensions, gross_amount, net_amount};
+use crate::purchase::{allocate, max_tokens_for_budget, Order};
+use crate::quote::{check_price, verify, PurchaseQuote};
+use crate::{
+    AcceptedMint, OracleConfig, OracleSource, PresaleConfig, Purchases, ReferralData, Round,
+    TokenContribution, UserContribution, ACCEPTED_MINT_SEED, MAX_BASIS_POINTS,
+    MAX_CONTRIBUTION_USD_PER_USER, MAX_TOKEN_CAP, ORACLE_CONFIG_SEED, PRESALE_SEED, ROUND_SEED,
+    TOKEN_CONTRIBUTION_SEED, USDC_DECIMALS, USER_CONTRIBUTION_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+#[derive(Accounts)]
+pub struct BuyWithToken<'info> {
+    /// Required unless the purchase carries a quote signed by the cosigner
+    #[account(address = presale_config.cosigner @ ErrorCode::Unauthorized)]
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.current_round.to_le_bytes().as_ref()
+        ],
+        bump = active_round.bump
+    )]
+    pub active_round: Account<'info, Round>,
+
+    /// Next scheduled round, required once the active round is sold out or has ended
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            (presale_config.current_round + 1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Option<Account<'info, Round>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserContribution::DISCRIMINATOR.len() + UserContribution::INIT_SPACE,
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub user_contribution: Account<'info, UserContribution>,
+
+    #[account(
+        has_one = mint @ ErrorCode::InvalidPaymentToken,
+        has_one = treasury_ata @ ErrorCode::InvalidAcceptedMint,
+        seeds = [ACCEPTED_MINT_SEED.as_bytes(), presale_config.key().as_ref(), mint.key().as_ref()],
+        bump = accepted_mint.bump
+    )]
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    /// Staleness and confidence guards applied to the mint's feed
+    #[account(
+        seeds = [ORACLE_CONFIG_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// Price of the payment token, required unless it is a stablecoin
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub treasury_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the payment instead of the treasury while escrow is enabled
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Records the escrowed payment for refunds, required while escrow is enabled
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = TokenContribution::DISCRIMINATOR.len() + TokenContribution::INIT_SPACE,
+        seeds = [
+            TOKEN_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            mint.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub token_contribution: Option<Account<'info, TokenContribution>>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Instructions sysvar, required with a quote to find its ed25519 signature check
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<BuyWithToken>,
+    order: Order,
+    referral: Option<ReferralData>,
+    quote: Option<PurchaseQuote>,
+) -> Result<()> {
+    require!(
+        !ctx.accounts.presale_config.finalized,
+        ErrorCode::PresaleEnded
+    );
+    require!(
+        !ctx.accounts.presale_config.paused,
+        ErrorCode::PresalePaused
+    );
+    require!(
+        ctx.accounts.accepted_mint.enabled,
+        ErrorCode::PaymentMethodDisabled
+    );
+    require_eq!(
+        ctx.accounts.presale_config.current_round,
+        ctx.accounts.active_round.round_id,
+        ErrorCode::InvalidRoundConfig
+    );
+
+    let accepted_mint = &ctx.accounts.accepted_mint;
+    let now = Clock::get()?.unix_timestamp;
+
+    // Stablecoins convert 1:1, i.e. a price of 1 with exponent 0
+    let (price_data, token_price_usd) = match accepted_mint.price_feed {
+        Some(price_feed) => {
+            let price_update = ctx
+                .accounts
+                .price_update
+                .as_ref()
+                .ok_or(ErrorCode::InvalidOracleAccount)?;
+            require_keys_eq!(
+                price_update.key(),
+                price_feed.price_account,
+                ErrorCode::InvalidOracleAccount
+            );
+            let price_data = price_update.get_price_no_older_than(
+                &Clock::get()?,
+                ctx.accounts.oracle_config.max_staleness,
+                &price_feed.feed_id,
+            )?;
+            let token_price_usd = checked_price(&price_data, &ctx.accounts.oracle_config)?;
+            (Some(price_data), token_price_usd)
+        }
+        None => (None, 1),
+    };
+    let exponent = price_data
+        .as_ref()
+        .map_or(0, |price_data| price_data.exponent);
+    let mint_info = ctx.accounts.mint.to_account_info();
+    // A transfer hook program can be set after the mint was accepted
+    check_extensions(
+        &mint_info,
+        ctx.accounts.accepted_mint.allow_permanent_delegate,
+    )?;
+    let epoch = Clock::get()?.epoch;
+
+    let user_contribution = &mut ctx.accounts.user_contribution;
+    if user_contribution.user == Pubkey::default() {
+        user_contribution.set_inner(UserContribution::new(
+            ctx.accounts.user.key(),
+            ctx.bumps.user_contribution,
+        ));
+    }
+
+    let bonus_bps = match &referral {
+        Some(ref referral_data) => {
+            require_gte!(MAX_BASIS_POINTS as u16, referral_data.bonus_percent);
+            referral_data.bonus_percent
+        }
+        None => 0,
+    };
+
+    let (token_amount, payment_amount, max_payment) = match order {
+        Order::ExactOut {
+            token_amount,
+            max_payment,
+        } => (token_amount, None, max_payment),
+        Order::ExactIn {
+            payment_amount,
+            min_tokens_out,
+        } => {
+            let token_amount = max_tokens_for_budget(
+                &ctx.accounts.presale_config,
+                &ctx.accounts.active_round,
+                ctx.accounts.next_round.as_deref(),
+                usd_for_amount(
+                    net_amount(&mint_info, payment_amount, epoch)?,
+                    token_price_usd,
+                    exponent,
+                    accepted_mint.decimals,
+                )?,
+                bonus_bps,
+                now,
+            )?;
+            require_gte!(
+                token_amount,
+                min_tokens_out,
+                ErrorCode::InsufficientTokensOut
+            );
+            (token_amount, Some(payment_amount), None)
+        }
+    };
+    require_gt!(token_amount, 0);
+
+    match &quote {
+        Some(quote) => verify(
+            quote,
+            ctx.accounts.presale_config.key(),
+            ctx.accounts.presale_config.cosigner,
+            token_amount,
+            user_contribution,
+            ctx.accounts
+                .instructions_sysvar
+                .as_ref()
+                .ok_or(ErrorCode::InvalidQuoteSignature)?,
+            now,
+        )?,
+        None => require!(ctx.accounts.cosigner.is_some(), ErrorCode::Unauthorized),
+    }
+
+    let allocation = allocate(
+        &mut ctx.accounts.presale_config,
+        &mut ctx.accounts.active_round,
+        ctx.accounts.next_round.as_deref_mut(),
+        token_amount,
+        bonus_bps,
+        now,
+    )?;
+    let bonus_tokens = allocation.bonus_tokens;
+    if let Some(quote) = &quote {
+        check_price(quote, allocation.total_cost_usd, token_amount)?;
+    }
+    let total_cost_usd = allocation.total_cost_usd; // USD cost * 10^12
+
+    // Exact-in purchases spend the whole payment, the remainder is worth less than one token unit.
+    // Exact-out payments are grossed up to cover Token-2022 transfer fees.
+    let amount_paid = match payment_amount {
+        Some(payment_amount) => payment_amount,
+        None => gross_amount(
+            &mint_info,
+            amount_for_usd(
+                total_cost_usd,
+                token_price_usd,
+                exponent,
+                accepted_mint.decimals,
+            )?,
+            epoch,
+        )?,
+    };
+
+    require_gt!(amount_paid, 0);
+    if let Some(max_payment) = max_payment {
+        require_gte!(max_payment, amount_paid, ErrorCode::MaxPaymentExceeded);
+    }
+
+    let contributed_amount_usd = total_cost_usd
+        .checked_div(10u128.pow(USDC_DECIMALS as u32))
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+
+    let token_amount_total = token_amount + bonus_tokens;
+    user_contribution.total_contributed_usd += contributed_amount_usd;
+    user_contribution.total_tokens_purchased += token_amount_total;
+    for fill in allocation.fills.iter() {
+        user_contribution.tokens_purchased_per_round[(fill.round_id - 1) as usize] +=
+            fill.amount_tokens + fill.amount_referral_bonus_tokens;
+    }
+    ctx.accounts.presale_config.total_allocated_tokens += token_amount_total;
+    ctx.accounts.presale_config.total_raised_usd += contributed_amount_usd;
+
+    require_gte!(
+        MAX_CONTRIBUTION_USD_PER_USER,
+        user_contribution.total_contributed_usd,
+        ErrorCode::ExceedsMaxContribution
+    );
+
+    require_gte!(
+        MAX_TOKEN_CAP,
+        ctx.accounts.presale_config.total_allocated_tokens,
+        ErrorCode::HardCapReached
+    );
+
+    let destination = if ctx.accounts.presale_config.escrow_enabled {
+        let escrow_ata = ctx
+            .accounts
+            .escrow_ata
+            .as_ref()
+            .ok_or(ErrorCode::MissingEscrowAccount)?;
+        let token_contribution = ctx
+            .accounts
+            .token_contribution
+            .as_mut()
+            .ok_or(ErrorCode::MissingEscrowAccount)?;
+        if token_contribution.user == Pubkey::default() {
+            token_contribution.set_inner(TokenContribution {
+                user: ctx.accounts.user.key(),
+                mint: ctx.accounts.mint.key(),
+                amount: 0,
+                purchases: Purchases::default(),
+                bump: ctx
+                    .bumps
+                    .token_contribution
+                    .ok_or(ErrorCode::MissingEscrowAccount)?,
+            });
+        }
+        // Refunds return what the escrow received, after any transfer fee
+        token_contribution.amount = token_contribution
+            .amount
+            .checked_add(net_amount(&mint_info, amount_paid, epoch)?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        token_contribution
+            .purchases
+            .add(contributed_amount_usd, &allocation.fills)?;
+        user_contribution
+            .token_purchases
+            .add(contributed_amount_usd, &allocation.fills)?;
+        escrow_ata.to_account_info()
+    } else {
+        ctx.accounts.treasury_ata.to_account_info()
+    };
+
+    let transfer_accounts = TransferChecked {
+        from: ctx.accounts.user_ata.to_account_info(),
+        to: destination,
+        authority: ctx.accounts.user.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+    };
+
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_accounts,
+    );
+
+    transfer_checked(transfer_ctx, amount_paid, ctx.accounts.mint.decimals)?;
+
+    emit!(Contributed {
+        contributor: ctx.accounts.user.key(),
+        stage_id: allocation.first_round_id(),
+        amount_tokens: token_amount,
+        amount_referral_bonus_tokens: bonus_tokens,
+        contributed_amount_usd,
+        referral,
+        asset: Asset::Token {
+            mint: ctx.accounts.mint.key(),
+        },
+        fills: allocation.fills,
+        amount_paid,
+        oracle_price: price_data.map(|price_data| OraclePrice {
+            price: price_data.price,
+            conf: price_data.conf,
+            exponent: price_data.exponent,
+            publish_time: price_data.publish_time,
+            source: OracleSource::Pyth,
+        }),
+    });
+
+    Ok(())
+}
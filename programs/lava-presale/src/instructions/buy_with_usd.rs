@@ -1,20 +1,27 @@
 use crate::error::ErrorCode;
 use crate::events::{Asset, Contributed};
+use crate::payment_mint::{check_extensions, gross_amount, net_amount};
+use crate::purchase::{allocate, max_tokens_for_budget, Order};
+use crate::quote::{check_price, verify, PurchaseQuote};
 use crate::{
-    PresaleConfig, ReferralData, Round, UserContribution, BASIS_POINTS, MAX_BASIS_POINTS,
-    MAX_CONTRIBUTION_USD_PER_USER, MAX_TOKEN_CAP, PRESALE_SEED, ROUND_SEED, USDC_DECIMALS,
-    USDC_MINT, USDT_MINT, USER_CONTRIBUTION_SEED,
+    PresaleConfig, ReferralData, Round, UserContribution, MAX_BASIS_POINTS,
+    MAX_CONTRIBUTION_USD_PER_USER, MAX_TOKEN_CAP, PAYMENT_METHOD_USDC, PAYMENT_METHOD_USDT,
+    PRESALE_SEED, ROUND_SEED, USDC_DECIMALS, USDC_MINT, USDT_MINT, USER_CONTRIBUTION_SEED,
 };
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::Token;
-use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TransferChecked};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 #[derive(Accounts)]
 pub struct BuyWithUsd<'info> {
-    pub authority: Signer<'info>,
+    /// Required unless the purchase carries a quote signed by the cosigner
+    #[account(address = presale_config.cosigner @ ErrorCode::Unauthorized)]
+    pub cosigner: Option<Signer<'info>>,
 
-    /// CHECK: Treasury wallet that receives funds
+    /// CHECK: Treasury wallet that receives funds when escrow is disabled
     pub treasury: UncheckedAccount<'info>,
 
     #[account(mut)]
@@ -22,24 +29,44 @@ pub struct BuyWithUsd<'info> {
 
     #[account(
         mut,
-        has_one = authority @ ErrorCode::Unauthorized,
         has_one = treasury @ ErrorCode::Unauthorized,
-        seeds = [PRESALE_SEED.as_bytes()],
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump = presale_config.bump
     )]
     pub presale_config: Account<'info, PresaleConfig>,
 
     #[account(
-        seeds = [ROUND_SEED.as_bytes(), presale_config.current_round.to_le_bytes().as_ref()],
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.current_round.to_le_bytes().as_ref()
+        ],
         bump = active_round.bump
     )]
     pub active_round: Account<'info, Round>,
 
+    /// Next scheduled round, required once the active round is sold out or has ended
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            (presale_config.current_round + 1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Option<Account<'info, Round>>,
+
     #[account(
         init_if_needed,
         payer = user,
         space = UserContribution::DISCRIMINATOR.len() + UserContribution::INIT_SPACE,
-        seeds = [USER_CONTRIBUTION_SEED.as_bytes(), user.key().as_ref()],
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
         bump
     )]
     pub user_contribution: Account<'info, UserContribution>,
@@ -52,6 +79,14 @@ pub struct BuyWithUsd<'info> {
     )]
     pub user_ata: InterfaceAccount<'info, TokenAccount>,
 
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_ata: InterfaceAccount<'info, TokenAccount>,
+
     #[account(
         mut,
         associated_token::mint = mint,
@@ -65,32 +100,35 @@ pub struct BuyWithUsd<'info> {
     )]
     pub mint: InterfaceAccount<'info, Mint>,
 
+    /// CHECK: Instructions sysvar, required with a quote to find its ed25519 signature check
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 pub fn handler(
     ctx: Context<BuyWithUsd>,
-    token_amount: u64,
+    order: Order,
     referral: Option<ReferralData>,
+    quote: Option<PurchaseQuote>,
 ) -> Result<()> {
     require!(
         !ctx.accounts.presale_config.finalized,
         ErrorCode::PresaleEnded
     );
-    require_gt!(token_amount, 0);
+    require!(
+        !ctx.accounts.presale_config.paused,
+        ErrorCode::PresalePaused
+    );
     require_eq!(
         ctx.accounts.presale_config.current_round,
         ctx.accounts.active_round.round_id,
         ErrorCode::InvalidRoundConfig
     );
 
-    require!(
-        ctx.accounts.active_round.is_active(),
-        ErrorCode::RoundNotActive
-    );
-
     let mint = &ctx.accounts.mint;
 
     require!(
@@ -98,41 +136,128 @@ pub fn handler(
         ErrorCode::InvalidPaymentToken
     );
 
+    let payment_method = if mint.key() == USDC_MINT {
+        PAYMENT_METHOD_USDC
+    } else {
+        PAYMENT_METHOD_USDT
+    };
+    require!(
+        ctx.accounts
+            .presale_config
+            .is_payment_method_enabled(payment_method),
+        ErrorCode::PaymentMethodDisabled
+    );
+
+    let mint_info = mint.to_account_info();
+    check_extensions(&mint_info, false)?;
+    let epoch = Clock::get()?.epoch;
+
     let user_contribution = &mut ctx.accounts.user_contribution;
-    if user_contribution.total_contributed_usd == 0 {
-        user_contribution.set_inner(UserContribution {
-            user: ctx.accounts.user.key(),
-            total_contributed_usd: 0,
-            total_tokens_purchased: 0,
-            bump: ctx.bumps.user_contribution,
-        });
+    if user_contribution.user == Pubkey::default() {
+        user_contribution.set_inner(UserContribution::new(
+            ctx.accounts.user.key(),
+            ctx.bumps.user_contribution,
+        ));
     }
-    let round = &ctx.accounts.active_round;
 
-    let bonus_tokens = match &referral {
+    let bonus_bps = match &referral {
         Some(ref referral_data) => {
             require_gte!(MAX_BASIS_POINTS as u16, referral_data.bonus_percent);
-            token_amount
-                .checked_mul(referral_data.bonus_percent as u64)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(BASIS_POINTS as u64)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+            referral_data.bonus_percent
         }
-
         None => 0,
     };
 
-    let token_price_usd = round.token_price_usd as u128;
-    let total_cost_usd = (token_amount as u128)
-        .checked_mul(token_price_usd)
-        .ok_or(ErrorCode::ArithmeticOverflow)?
-        .checked_div(10_u128.pow(USDC_DECIMALS as u32))
-        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+    let now = Clock::get()?.unix_timestamp;
+    let (token_amount, payment_amount) = match order {
+        Order::ExactOut { token_amount, .. } => (token_amount, None),
+        Order::ExactIn {
+            payment_amount,
+            min_tokens_out,
+        } => {
+            let token_amount = max_tokens_for_budget(
+                &ctx.accounts.presale_config,
+                &ctx.accounts.active_round,
+                ctx.accounts.next_round.as_deref(),
+                (net_amount(&mint_info, payment_amount, epoch)? as u128)
+                    .checked_mul(10_u128.pow(USDC_DECIMALS as u32))
+                    .ok_or(ErrorCode::ArithmeticOverflow)?,
+                bonus_bps,
+                now,
+            )?;
+            require_gte!(
+                token_amount,
+                min_tokens_out,
+                ErrorCode::InsufficientTokensOut
+            );
+            (token_amount, Some(payment_amount))
+        }
+    };
+    require_gt!(token_amount, 0);
+
+    match &quote {
+        Some(quote) => verify(
+            quote,
+            ctx.accounts.presale_config.key(),
+            ctx.accounts.presale_config.cosigner,
+            token_amount,
+            user_contribution,
+            ctx.accounts
+                .instructions_sysvar
+                .as_ref()
+                .ok_or(ErrorCode::InvalidQuoteSignature)?,
+            now,
+        )?,
+        None => require!(ctx.accounts.cosigner.is_some(), ErrorCode::Unauthorized),
+    }
+
+    let allocation = allocate(
+        &mut ctx.accounts.presale_config,
+        &mut ctx.accounts.active_round,
+        ctx.accounts.next_round.as_deref_mut(),
+        token_amount,
+        bonus_bps,
+        now,
+    )?;
+    let bonus_tokens = allocation.bonus_tokens;
+    if let Some(quote) = &quote {
+        check_price(quote, allocation.total_cost_usd, token_amount)?;
+    }
+
+    // Exact-in purchases spend the whole payment, the remainder is worth less than one token unit.
+    // Only what arrives after Token-2022 transfer fees is credited, so exact-out payments are
+    // grossed up to cover the fee.
+    let (amount_paid, total_cost_usd) = match payment_amount {
+        Some(payment_amount) => (
+            payment_amount,
+            net_amount(&mint_info, payment_amount, epoch)?,
+        ),
+        None => {
+            let total_cost_usd = allocation
+                .total_cost_usd
+                .checked_div(10_u128.pow(USDC_DECIMALS as u32))
+                .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+            (
+                gross_amount(&mint_info, total_cost_usd, epoch)?,
+                total_cost_usd,
+            )
+        }
+    };
 
     let token_amount_total = token_amount + bonus_tokens;
     user_contribution.total_contributed_usd += total_cost_usd;
+    if mint.key() == USDC_MINT {
+        user_contribution.contributed_usdc += total_cost_usd;
+    } else {
+        user_contribution.contributed_usdt += total_cost_usd;
+    }
     user_contribution.total_tokens_purchased += token_amount_total;
+    for fill in allocation.fills.iter() {
+        user_contribution.tokens_purchased_per_round[(fill.round_id - 1) as usize] +=
+            fill.amount_tokens + fill.amount_referral_bonus_tokens;
+    }
     ctx.accounts.presale_config.total_allocated_tokens += token_amount_total;
+    ctx.accounts.presale_config.total_raised_usd += total_cost_usd;
 
     require_gte!(
         MAX_CONTRIBUTION_USD_PER_USER,
@@ -148,7 +273,11 @@ pub fn handler(
 
     let transfer_accounts = TransferChecked {
         from: ctx.accounts.user_ata.to_account_info(),
-        to: ctx.accounts.treasury_ata.to_account_info(),
+        to: if ctx.accounts.presale_config.escrow_enabled {
+            ctx.accounts.escrow_ata.to_account_info()
+        } else {
+            ctx.accounts.treasury_ata.to_account_info()
+        },
         authority: ctx.accounts.user.to_account_info(),
         mint: ctx.accounts.mint.to_account_info(),
     };
@@ -158,16 +287,19 @@ pub fn handler(
         transfer_accounts,
     );
 
-    transfer_checked(transfer_ctx, total_cost_usd, ctx.accounts.mint.decimals)?;
+    transfer_checked(transfer_ctx, amount_paid, ctx.accounts.mint.decimals)?;
 
     emit!(Contributed {
         contributor: ctx.accounts.user.key(),
-        stage_id: ctx.accounts.active_round.round_id,
+        stage_id: allocation.first_round_id(),
         amount_tokens: token_amount,
         amount_referral_bonus_tokens: bonus_tokens,
         contributed_amount_usd: total_cost_usd,
         referral,
-        asset: Asset::USD
+        asset: Asset::USD,
+        fills: allocation.fills,
+        amount_paid,
+        oracle_price: None,
     });
 
     Ok(())
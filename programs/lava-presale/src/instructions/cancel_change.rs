@@ -0,0 +1,37 @@
+use crate::error::ErrorCode;
+use crate::events::ChangeCancelled;
+use crate::{PendingChange, PresaleConfig, PENDING_CHANGE_SEED, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CancelChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [
+            PENDING_CHANGE_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            pending_change.change_id.to_le_bytes().as_ref()
+        ],
+        bump = pending_change.bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+}
+
+pub fn handler(ctx: Context<CancelChange>) -> Result<()> {
+    emit!(ChangeCancelled {
+        change_id: ctx.accounts.pending_change.change_id,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use crate::error::ErrorCode;
+use crate::events::RoundCancelled;
+use crate::{PresaleConfig, Round, PRESALE_SEED, ROUND_SEED, START_ROUND_ID};
+use anchor_lang::prelude::*;
+
+/// Removes the last scheduled round as long as it has not started yet
+#[derive(Accounts)]
+pub struct CancelRound<'info> {
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = operator @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        close = operator,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+}
+
+pub fn handler(ctx: Context<CancelRound>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    require!(
+        !presale_config.finalized,
+        ErrorCode::PresaleAlreadyFinalized
+    );
+
+    let round = &ctx.accounts.round;
+    require!(
+        round.round_id > presale_config.current_round,
+        ErrorCode::RoundAlreadyStarted
+    );
+    require!(
+        Clock::get()?.unix_timestamp < round.start_time,
+        ErrorCode::RoundAlreadyStarted
+    );
+
+    // The active round and the first round always stay scheduled
+    require_gt!(
+        presale_config.last_round,
+        presale_config.current_round,
+        ErrorCode::RoundAlreadyStarted
+    );
+    require_gt!(
+        presale_config.last_round,
+        START_ROUND_ID,
+        ErrorCode::InvalidRoundConfig
+    );
+
+    presale_config.last_round -= 1;
+
+    emit!(RoundCancelled {
+        round_id: round.round_id,
+    });
+
+    Ok(())
+}
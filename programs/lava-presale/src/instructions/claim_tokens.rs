@@ -0,0 +1,134 @@
+use crate::error::ErrorCode;
+use crate::events::TokensClaimed;
+use crate::{
+    PresaleConfig, Round, UserContribution, VestingSchedule, MAX_STAGES, PRESALE_SEED, ROUND_SEED,
+    START_ROUND_ID, TOKEN_VAULT_SEED, USER_CONTRIBUTION_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct ClaimTokens<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        has_one = token_mint @ ErrorCode::InvalidTokenMint,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump = user_contribution.bump
+    )]
+    pub user_contribution: Account<'info, UserContribution>,
+
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = presale_config,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<ClaimTokens>) -> Result<()> {
+    let presale_config = &ctx.accounts.presale_config;
+    require!(presale_config.finalized, ErrorCode::PresaleNotFinalized);
+    require_neq!(presale_config.tge_timestamp, 0, ErrorCode::TgeNotSet);
+
+    // The Round accounts of every round the user bought in are passed as remaining accounts,
+    // so each round's vesting schedule is applied to the tokens bought in that round
+    let mut round_vesting: [Option<VestingSchedule>; MAX_STAGES] = [None; MAX_STAGES];
+    for round_info in ctx.remaining_accounts.iter() {
+        require_keys_eq!(*round_info.owner, crate::ID, ErrorCode::InvalidRoundConfig);
+        let round = Round::try_deserialize(&mut &round_info.try_borrow_data()?[..])?;
+        require!(
+            round.round_id >= START_ROUND_ID && round.round_id as usize <= MAX_STAGES,
+            ErrorCode::InvalidRoundConfig
+        );
+        // Rounds of other presales share round ids, only this presale's PDA is accepted
+        let round_key = Pubkey::create_program_address(
+            &[
+                ROUND_SEED.as_bytes(),
+                presale_config.key().as_ref(),
+                round.round_id.to_le_bytes().as_ref(),
+                &[round.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidRoundConfig)?;
+        require_keys_eq!(round_info.key(), round_key, ErrorCode::InvalidRoundConfig);
+        round_vesting[(round.round_id - 1) as usize] = Some(round.vesting);
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let user_contribution = &mut ctx.accounts.user_contribution;
+    let claimable =
+        user_contribution.claimable(&round_vesting, presale_config.tge_timestamp, now)?;
+
+    require_gt!(claimable, 0, ErrorCode::NothingToClaim);
+
+    user_contribution.claimed_tokens = user_contribution
+        .claimed_tokens
+        .checked_add(claimable)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    let presale_id = presale_config.presale_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        PRESALE_SEED.as_bytes(),
+        presale_id.as_ref(),
+        &[presale_config.bump],
+    ]];
+
+    let transfer_accounts = TransferChecked {
+        from: ctx.accounts.token_vault.to_account_info(),
+        to: ctx.accounts.user_token_account.to_account_info(),
+        authority: presale_config.to_account_info(),
+        mint: ctx.accounts.token_mint.to_account_info(),
+    };
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_accounts,
+        signer_seeds,
+    );
+
+    transfer_checked(transfer_ctx, claimable, ctx.accounts.token_mint.decimals)?;
+
+    emit!(TokensClaimed {
+        user: ctx.accounts.user.key(),
+        amount_tokens: claimable,
+        total_claimed_tokens: user_contribution.claimed_tokens,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::error::ErrorCode;
+use crate::events::RoundsConfigured;
+use crate::{CreateRoundData, PresaleConfig, Round, MAX_STAGES, PRESALE_SEED, ROUND_SEED};
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
+
+/// Schedules several future rounds at once. The Round PDAs to create are passed as remaining
+/// accounts, in the same order as `rounds`.
+#[derive(Accounts)]
+pub struct ConfigureRounds<'info> {
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = operator @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = previous_round.bump
+    )]
+    pub previous_round: Account<'info, Round>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ConfigureRounds<'info>>,
+    rounds: Vec<CreateRoundData>,
+) -> Result<()> {
+    require!(
+        !ctx.accounts.presale_config.finalized,
+        ErrorCode::PresaleAlreadyFinalized
+    );
+    // Once the final round has ended the sale is over, new rounds would close the refund window
+    require!(
+        Clock::get()?.unix_timestamp <= ctx.accounts.previous_round.end_time,
+        ErrorCode::PresaleEnded
+    );
+    require!(!rounds.is_empty(), ErrorCode::InvalidRoundConfig);
+    require_eq!(
+        rounds.len(),
+        ctx.remaining_accounts.len(),
+        ErrorCode::MissingRoundAccount
+    );
+
+    let first_round_id = ctx.accounts.presale_config.last_round + 1;
+    require!(
+        first_round_id as usize + rounds.len() - 1 <= MAX_STAGES,
+        ErrorCode::InvalidRoundConfig
+    );
+
+    let presale_key = ctx.accounts.presale_config.key();
+    let space = Round::DISCRIMINATOR.len() + Round::INIT_SPACE;
+
+    let mut previous_end_time = ctx.accounts.previous_round.end_time;
+    let mut previous_price_usd = ctx.accounts.previous_round.token_price_usd;
+
+    for (index, (data, round_info)) in rounds.iter().zip(ctx.remaining_accounts).enumerate() {
+        data.validate(previous_end_time, previous_price_usd)?;
+
+        let round_id = first_round_id + index as u8;
+        let round_id_bytes = round_id.to_le_bytes();
+        let (round_key, bump) = Pubkey::find_program_address(
+            &[
+                ROUND_SEED.as_bytes(),
+                presale_key.as_ref(),
+                round_id_bytes.as_ref(),
+            ],
+            ctx.program_id,
+        );
+        require_keys_eq!(round_info.key(), round_key, ErrorCode::InvalidRoundConfig);
+
+        create_round_account(
+            &ctx.accounts.operator.to_account_info(),
+            round_info,
+            &ctx.accounts.system_program.to_account_info(),
+            &[&[
+                ROUND_SEED.as_bytes(),
+                presale_key.as_ref(),
+                round_id_bytes.as_ref(),
+                &[bump],
+            ]],
+            space,
+            ctx.program_id,
+        )?;
+
+        Round::new(round_id, data, bump)
+            .try_serialize(&mut &mut round_info.try_borrow_mut_data()?[..])?;
+
+        previous_end_time = data.end_time;
+        previous_price_usd = data.token_price_usd;
+    }
+
+    let last_round_id = first_round_id + rounds.len() as u8 - 1;
+    ctx.accounts.presale_config.last_round = last_round_id;
+
+    emit!(RoundsConfigured {
+        first_round_id,
+        last_round_id,
+    });
+
+    Ok(())
+}
+
+/// Creates the Round PDA like Anchor's `init` does: a PDA that already holds lamports cannot be
+/// created with `create_account`, so it is topped up to rent exemption, allocated and assigned
+fn create_round_account<'info>(
+    payer: &AccountInfo<'info>,
+    round_info: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    space: usize,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = round_info.lamports();
+
+    if current_lamports == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: round_info.clone(),
+                },
+                signer_seeds,
+            ),
+            rent_exempt_lamports,
+            space as u64,
+            program_id,
+        );
+    }
+
+    let shortfall = rent_exempt_lamports.saturating_sub(current_lamports);
+    if shortfall > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: round_info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: round_info.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: round_info.clone(),
+            },
+            signer_seeds,
+        ),
+        program_id,
+    )
+}
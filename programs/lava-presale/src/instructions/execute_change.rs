@@ -0,0 +1,185 @@
+use crate::error::ErrorCode;
+use crate::events::{
+    AcceptedMintUpdated, ChangeExecuted, OracleConfigUpdated, SoftCapUpdated, TimelockDelayUpdated,
+    TreasuryUpdated,
+};
+use crate::{
+    AcceptedMint, ConfigChange, OracleConfig, PendingChange, PresaleConfig, Round,
+    ACCEPTED_MINT_SEED, ORACLE_CONFIG_SEED, PENDING_CHANGE_SEED, PRESALE_SEED, ROUND_SEED,
+    USDC_MINT, USDT_MINT,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Applies a queued change once its timelock has elapsed. Only the accounts the change touches
+/// need to be passed.
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [
+            PENDING_CHANGE_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            pending_change.change_id.to_le_bytes().as_ref()
+        ],
+        bump = pending_change.bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    #[account(
+        mut,
+        seeds = [ORACLE_CONFIG_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Option<Account<'info, OracleConfig>>,
+
+    #[account(
+        mut,
+        seeds = [
+            ACCEPTED_MINT_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            accepted_mint.mint.as_ref()
+        ],
+        bump = accepted_mint.bump
+    )]
+    pub accepted_mint: Option<Account<'info, AcceptedMint>>,
+
+    /// Last scheduled round, required for SoftCap changes
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = final_round.bump
+    )]
+    pub final_round: Option<Account<'info, Round>>,
+
+    /// USDC ATA of the new treasury
+    pub treasury_usdc_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// USDT ATA of the new treasury
+    pub treasury_usdt_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+pub fn handler(ctx: Context<ExecuteChange>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let pending_change = &ctx.accounts.pending_change;
+    require!(
+        pending_change.is_effective(now),
+        ErrorCode::TimelockNotElapsed
+    );
+
+    let presale_config = &mut ctx.accounts.presale_config;
+    match pending_change.change {
+        ConfigChange::Treasury { treasury } => {
+            check_treasury_ata(ctx.accounts.treasury_usdc_ata.as_ref(), treasury, USDC_MINT)?;
+            check_treasury_ata(ctx.accounts.treasury_usdt_ata.as_ref(), treasury, USDT_MINT)?;
+
+            let previous_treasury = presale_config.treasury;
+            presale_config.treasury = treasury;
+
+            emit!(TreasuryUpdated {
+                previous_treasury,
+                treasury,
+            });
+        }
+        ConfigChange::SoftCap { soft_cap_usd } => {
+            let final_round = ctx
+                .accounts
+                .final_round
+                .as_ref()
+                .ok_or(ErrorCode::MissingChangeAccount)?;
+            require!(
+                !presale_config.is_soft_cap_locked(final_round, now),
+                ErrorCode::SoftCapLocked
+            );
+            presale_config.soft_cap_usd = soft_cap_usd;
+
+            emit!(SoftCapUpdated { soft_cap_usd });
+        }
+        ConfigChange::OracleConfig { data } => {
+            let oracle_config = ctx
+                .accounts
+                .oracle_config
+                .as_mut()
+                .ok_or(ErrorCode::MissingChangeAccount)?;
+            let bump = oracle_config.bump;
+            oracle_config.set_inner(OracleConfig::new(&data, bump));
+
+            emit!(OracleConfigUpdated {
+                feed_id: data.feed_id,
+                price_account: data.price_account,
+                max_staleness: data.max_staleness,
+                max_price_conf_bps: data.max_price_conf_bps,
+                conservative_pricing: data.conservative_pricing,
+                primary_source: data.primary_source,
+                switchboard_feed: data.switchboard_feed,
+                max_deviation_bps: data.max_deviation_bps,
+            });
+        }
+        ConfigChange::AcceptedMint { mint, data } => {
+            let accepted_mint = ctx
+                .accounts
+                .accepted_mint
+                .as_mut()
+                .ok_or(ErrorCode::MissingChangeAccount)?;
+            require_keys_eq!(accepted_mint.mint, mint, ErrorCode::MissingChangeAccount);
+
+            accepted_mint.price_feed = data.price_feed;
+            accepted_mint.stable = data.stable;
+            accepted_mint.enabled = data.enabled;
+            accepted_mint.allow_permanent_delegate = data.allow_permanent_delegate;
+
+            emit!(AcceptedMintUpdated {
+                mint,
+                price_feed: data.price_feed,
+                stable: data.stable,
+                treasury_ata: accepted_mint.treasury_ata,
+                enabled: data.enabled,
+                allow_permanent_delegate: data.allow_permanent_delegate,
+            });
+        }
+        ConfigChange::TimelockDelay { timelock_delay } => {
+            presale_config.timelock_delay = timelock_delay;
+
+            emit!(TimelockDelayUpdated { timelock_delay });
+        }
+    }
+
+    emit!(ChangeExecuted {
+        change_id: pending_change.change_id,
+    });
+
+    Ok(())
+}
+
+/// Proceeds are withdrawn to the treasury's ATAs, so they must exist before it takes over
+fn check_treasury_ata(
+    treasury_ata: Option<&InterfaceAccount<TokenAccount>>,
+    treasury: Pubkey,
+    mint: Pubkey,
+) -> Result<()> {
+    let treasury_ata = treasury_ata.ok_or(ErrorCode::MissingChangeAccount)?;
+    let token_program = treasury_ata.to_account_info().owner;
+    require_keys_eq!(
+        treasury_ata.key(),
+        get_associated_token_address_with_program_id(&treasury, &mint, token_program),
+        ErrorCode::MissingChangeAccount
+    );
+
+    Ok(())
+}
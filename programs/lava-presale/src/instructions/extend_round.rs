@@ -0,0 +1,77 @@
+use crate::error::ErrorCode;
+use crate::events::RoundExtended;
+use crate::{PresaleConfig, Round, PRESALE_SEED, ROUND_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(round_id: u8)]
+pub struct ExtendRound<'info> {
+    pub operator: Signer<'info>,
+
+    #[account(
+        has_one = operator @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            round_id.to_le_bytes().as_ref()
+        ],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+
+    /// Required unless the extended round is the last scheduled one
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            round_id.wrapping_add(1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Option<Account<'info, Round>>,
+}
+
+pub fn handler(ctx: Context<ExtendRound>, round_id: u8, end_time: i64) -> Result<()> {
+    let presale_config = &ctx.accounts.presale_config;
+    require!(
+        !presale_config.finalized,
+        ErrorCode::PresaleAlreadyFinalized
+    );
+
+    let round = &mut ctx.accounts.round;
+    require!(
+        round.is_active(Clock::get()?.unix_timestamp),
+        ErrorCode::RoundNotActive
+    );
+    require!(end_time > round.end_time, ErrorCode::InvalidRoundConfig);
+
+    match &ctx.accounts.next_round {
+        Some(next_round) => require!(
+            end_time <= next_round.start_time,
+            ErrorCode::InvalidRoundConfig
+        ),
+        None => require_eq!(
+            round_id,
+            presale_config.last_round,
+            ErrorCode::MissingRoundAccount
+        ),
+    }
+
+    let previous_end_time = round.end_time;
+    round.end_time = end_time;
+
+    emit!(RoundExtended {
+        round_id,
+        previous_end_time,
+        end_time,
+    });
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use crate::error::ErrorCode;
 use anchor_lang::prelude::*;
 
-use crate::{PresaleConfig, MAX_STAGES, PRESALE_SEED};
+use crate::purchase::should_advance;
+use crate::{PresaleConfig, Round, PRESALE_SEED, ROUND_SEED};
 
 #[derive(Accounts)]
 pub struct FinalizePresale<'info> {
@@ -11,10 +12,20 @@ pub struct FinalizePresale<'info> {
     #[account(
         mut,
         has_one = authority,
-        seeds = [PRESALE_SEED.as_bytes()],
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump = presale_config.bump
     )]
     pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = final_round.bump
+    )]
+    pub final_round: Account<'info, Round>,
 }
 
 pub fn handler(ctx: Context<FinalizePresale>) -> Result<()> {
@@ -24,11 +35,23 @@ pub fn handler(ctx: Context<FinalizePresale>) -> Result<()> {
     );
     let presale_config = &mut ctx.accounts.presale_config;
 
+    // The sale is over once the last scheduled round sold out or ended
     require!(
-        presale_config.current_round == MAX_STAGES as u8,
+        presale_config.current_round == presale_config.last_round
+            && should_advance(&ctx.accounts.final_round, Clock::get()?.unix_timestamp),
         ErrorCode::PresaleNotFinalized
     );
 
+    // Escrowed sales below the soft cap are refunded instead. Without escrow the proceeds
+    // already reached the treasury, so the sale finalizes regardless and buyers can claim.
+    if presale_config.escrow_enabled {
+        require_gte!(
+            presale_config.total_raised_usd,
+            presale_config.soft_cap_usd,
+            ErrorCode::SoftCapNotReached
+        );
+    }
+
     presale_config.finalized = true;
 
     Ok(())
@@ -1,24 +1,46 @@
 use crate::constants::PRESALE_SEED;
+use crate::error::ErrorCode;
+use crate::events::PresaleInitialized;
+use crate::payment_mint::check_extensions;
 use crate::state::PresaleConfig;
-use crate::{CreateRoundData, Round, AUTHORITY, ROUND_SEED, START_ROUND_ID, USDC_MINT, USDT_MINT};
+use crate::{
+    CreateRoundData, Deployer, OracleConfig, OracleConfigData, OracleSource, Round, SolVault,
+    ALL_PAYMENT_METHODS, DEFAULT_MAX_PRICE_AGE, DEFAULT_MAX_PRICE_CONF_BPS,
+    DEFAULT_MAX_PRICE_DEVIATION_BPS, DEPLOYER_SEED, ORACLE_CONFIG_SEED, ROUND_SEED,
+    SOL_USD_FEED_ID, SOL_USD_PRICE_FEED_ACCOUNT, SOL_VAULT_SEED, START_ROUND_ID, USDC_MINT,
+    USDT_MINT,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::Token;
-use anchor_spl::token_interface::{Mint, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use pyth_solana_receiver_sdk::price_update::get_feed_id_from_hex;
 
 #[derive(Accounts)]
+#[instruction(presale_id: u64)]
 pub struct InitializePresale<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Program data holding the upgrade authority, required unless `deployer` is passed
     #[account(
-        mut,
-        address = AUTHORITY
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = ProgramData::owner()
     )]
-    pub authority: Signer<'info>,
+    pub program_data: Option<Account<'info, ProgramData>>,
+
+    /// Allowlist entry of the authority, required unless it is the upgrade authority
+    #[account(
+        seeds = [DEPLOYER_SEED.as_bytes(), authority.key().as_ref()],
+        bump = deployer.bump
+    )]
+    pub deployer: Option<Account<'info, Deployer>>,
 
     #[account(
         init,
         payer = authority,
         space = PresaleConfig::DISCRIMINATOR.len() + PresaleConfig::INIT_SPACE,
-        seeds = [PRESALE_SEED.as_bytes()],
+        seeds = [PRESALE_SEED.as_bytes(), presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_config: Account<'info, PresaleConfig>,
@@ -27,7 +49,11 @@ pub struct InitializePresale<'info> {
         init,
         payer = authority,
         space = Round::DISCRIMINATOR.len() + Round::INIT_SPACE,
-        seeds = [ROUND_SEED.as_bytes(), START_ROUND_ID.to_le_bytes().as_ref()],
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            START_ROUND_ID.to_le_bytes().as_ref()
+        ],
         bump
     )]
     pub round: Account<'info, Round>,
@@ -53,6 +79,42 @@ pub struct InitializePresale<'info> {
     )]
     pub treasury_usdt_ata: InterfaceAccount<'info, TokenAccount>,
 
+    #[account(
+        init,
+        payer = authority,
+        space = SolVault::DISCRIMINATOR.len() + SolVault::INIT_SPACE,
+        seeds = [SOL_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = OracleConfig::DISCRIMINATOR.len() + OracleConfig::INIT_SPACE,
+        seeds = [ORACLE_CONFIG_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_usdt_ata: InterfaceAccount<'info, TokenAccount>,
+
     #[account(address = USDC_MINT)]
     pub usdc_mint: InterfaceAccount<'info, Mint>,
 
@@ -60,29 +122,80 @@ pub struct InitializePresale<'info> {
     pub usdt_mint: InterfaceAccount<'info, Mint>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<InitializePresale>, first_stage: CreateRoundData) -> Result<()> {
+pub fn handler(
+    ctx: Context<InitializePresale>,
+    presale_id: u64,
+    first_stage: CreateRoundData,
+    soft_cap_usd: u64,
+    escrow_enabled: bool,
+) -> Result<()> {
+    let upgrade_authority = ctx
+        .accounts
+        .program_data
+        .as_ref()
+        .and_then(|program_data| program_data.upgrade_authority_address);
+    require!(
+        ctx.accounts.deployer.is_some() || upgrade_authority == Some(ctx.accounts.authority.key()),
+        ErrorCode::Unauthorized
+    );
+
+    first_stage.validate(0, 0)?;
+    check_extensions(&ctx.accounts.usdc_mint.to_account_info(), false)?;
+    check_extensions(&ctx.accounts.usdt_mint.to_account_info(), false)?;
+
     let presale_config = &mut ctx.accounts.presale_config;
 
     presale_config.set_inner(PresaleConfig {
+        presale_id,
         authority: ctx.accounts.authority.key(),
+        pending_authority: Pubkey::default(),
         treasury: ctx.accounts.treasury.key(),
+        token_mint: Pubkey::default(),
         current_round: START_ROUND_ID,
+        last_round: START_ROUND_ID,
         finalized: false,
         total_allocated_tokens: 0,
+        tge_timestamp: 0,
+        soft_cap_usd,
+        total_raised_usd: 0,
+        escrow_enabled,
+        paused: false,
+        enabled_payment_methods: ALL_PAYMENT_METHODS,
+        operator: ctx.accounts.authority.key(),
+        pauser: ctx.accounts.authority.key(),
+        cosigner: ctx.accounts.authority.key(),
+        timelock_delay: 0,
+        next_change_id: 0,
         bump: ctx.bumps.presale_config,
     });
 
     let stage = &mut ctx.accounts.round;
-    stage.set_inner(Round {
-        round_id: START_ROUND_ID,
-        start_time: first_stage.start_time,
-        end_time: first_stage.end_time,
-        token_price_usd: first_stage.token_price_usd,
-        bump: ctx.bumps.round,
+    stage.set_inner(Round::new(START_ROUND_ID, &first_stage, ctx.bumps.round));
+
+    ctx.accounts.sol_vault.bump = ctx.bumps.sol_vault;
+
+    ctx.accounts.oracle_config.set_inner(OracleConfig::new(
+        &OracleConfigData {
+            feed_id: get_feed_id_from_hex(SOL_USD_FEED_ID)?,
+            price_account: SOL_USD_PRICE_FEED_ACCOUNT,
+            max_staleness: DEFAULT_MAX_PRICE_AGE,
+            max_price_conf_bps: DEFAULT_MAX_PRICE_CONF_BPS,
+            conservative_pricing: false,
+            primary_source: OracleSource::Pyth,
+            switchboard_feed: Pubkey::default(),
+            max_deviation_bps: DEFAULT_MAX_PRICE_DEVIATION_BPS,
+        },
+        ctx.bumps.oracle_config,
+    ));
+
+    emit!(PresaleInitialized {
+        presale_id,
+        presale_config: ctx.accounts.presale_config.key(),
+        authority: ctx.accounts.authority.key(),
     });
 
     Ok(())
@@ -0,0 +1,53 @@
+use crate::error::ErrorCode;
+use crate::{PresaleConfig, LAVA_DECIMALS, PRESALE_SEED, TOKEN_VAULT_SEED};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct InitializeTokenVault<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mint::decimals = LAVA_DECIMALS,
+        mint::token_program = token_program
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// Vault holding the LAVA tokens paid out by `claim_tokens`, funded by the authority
+    #[account(
+        init,
+        payer = authority,
+        seeds = [TOKEN_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = presale_config,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitializeTokenVault>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+
+    require_keys_eq!(
+        presale_config.token_mint,
+        Pubkey::default(),
+        ErrorCode::TokenMintAlreadySet
+    );
+
+    presale_config.token_mint = ctx.accounts.token_mint.key();
+
+    Ok(())
+}
@@ -1,11 +1,69 @@
+pub mod accept_authority;
+pub mod add_accepted_mint;
+pub mod add_deployer;
+pub mod advance_round;
 pub mod buy_with_sol;
+pub mod buy_with_token;
 pub mod buy_with_usd;
+pub mod cancel_change;
+pub mod cancel_round;
+pub mod claim_tokens;
+pub mod configure_rounds;
+pub mod execute_change;
+pub mod extend_round;
 pub mod finalize_presale;
+pub mod grant_role;
 pub mod initialize_presale;
+pub mod initialize_token_vault;
+pub mod pause_presale;
+pub mod propose_authority;
+pub mod queue_change;
+pub mod refund;
+pub mod refund_token;
+pub mod remove_deployer;
+pub mod revoke_role;
 pub mod set_new_round;
+pub mod set_payment_methods;
+pub mod set_tge_timestamp;
+pub mod set_treasury;
+pub mod unpause_presale;
+pub mod update_accepted_mint;
+pub mod update_oracle_config;
+pub mod update_round;
+pub mod withdraw_proceeds;
+pub mod withdraw_token_proceeds;
 
+pub use accept_authority::*;
+pub use add_accepted_mint::*;
+pub use add_deployer::*;
+pub use advance_round::*;
 pub use buy_with_sol::*;
+pub use buy_with_token::*;
 pub use buy_with_usd::*;
+pub use cancel_change::*;
+pub use cancel_round::*;
+pub use claim_tokens::*;
+pub use configure_rounds::*;
+pub use execute_change::*;
+pub use extend_round::*;
 pub use finalize_presale::*;
+pub use grant_role::*;
 pub use initialize_presale::*;
+pub use initialize_token_vault::*;
+pub use pause_presale::*;
+pub use propose_authority::*;
+pub use queue_change::*;
+pub use refund::*;
+pub use refund_token::*;
+pub use remove_deployer::*;
+pub use revoke_role::*;
 pub use set_new_round::*;
+pub use set_payment_methods::*;
+pub use set_tge_timestamp::*;
+pub use set_treasury::*;
+pub use unpause_presale::*;
+pub use update_accepted_mint::*;
+pub use update_oracle_config::*;
+pub use update_round::*;
+pub use withdraw_proceeds::*;
+pub use withdraw_token_proceeds::*;
@@ -0,0 +1,31 @@
+use crate::error::ErrorCode;
+use crate::events::PresalePaused;
+use crate::{PresaleConfig, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct PausePresale<'info> {
+    pub pauser: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = pauser @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<PausePresale>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    require!(!presale_config.paused, ErrorCode::PresalePaused);
+
+    presale_config.paused = true;
+
+    emit!(PresalePaused {
+        pauser: ctx.accounts.pauser.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
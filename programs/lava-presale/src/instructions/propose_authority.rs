@@ -0,0 +1,30 @@
+use crate::error::ErrorCode;
+use crate::events::AuthorityProposed;
+use crate::{PresaleConfig, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+/// Proposing the default pubkey withdraws a pending proposal
+pub fn handler(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    presale_config.pending_authority = new_authority;
+
+    emit!(AuthorityProposed {
+        authority: presale_config.authority,
+        pending_authority: new_authority,
+    });
+
+    Ok(())
+}
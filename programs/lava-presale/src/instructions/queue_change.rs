@@ -0,0 +1,91 @@
+use crate::error::ErrorCode;
+use crate::events::ChangeQueued;
+use crate::{
+    ConfigChange, PendingChange, PresaleConfig, Round, PENDING_CHANGE_SEED, PRESALE_SEED,
+    ROUND_SEED,
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct QueueChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = PendingChange::DISCRIMINATOR.len() + PendingChange::INIT_SPACE,
+        seeds = [
+            PENDING_CHANGE_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.next_change_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// Last scheduled round, required for SoftCap changes
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = final_round.bump
+    )]
+    pub final_round: Option<Account<'info, Round>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<QueueChange>, change: ConfigChange) -> Result<()> {
+    change.validate()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let presale_config = &mut ctx.accounts.presale_config;
+    if let ConfigChange::SoftCap { .. } = change {
+        let final_round = ctx
+            .accounts
+            .final_round
+            .as_ref()
+            .ok_or(ErrorCode::MissingChangeAccount)?;
+        require!(
+            !presale_config.is_soft_cap_locked(final_round, now),
+            ErrorCode::SoftCapLocked
+        );
+    }
+
+    let change_id = presale_config.next_change_id;
+    let pending_change = PendingChange::new(
+        change_id,
+        change,
+        now,
+        presale_config
+            .timelock_delay
+            .max(change.min_delay(presale_config.escrow_enabled)),
+        ctx.bumps.pending_change,
+    )?;
+    let effective_at = pending_change.effective_at;
+
+    presale_config.next_change_id = change_id
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    ctx.accounts.pending_change.set_inner(pending_change);
+
+    emit!(ChangeQueued {
+        change_id,
+        change,
+        effective_at,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use crate::error::ErrorCode;
+use crate::events::Refunded;
+use crate::{
+    PresaleConfig, Round, SolVault, UserContribution, PRESALE_SEED, ROUND_SEED, SOL_VAULT_SEED,
+    USDC_MINT, USDT_MINT, USER_CONTRIBUTION_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = final_round.bump
+    )]
+    pub final_round: Account<'info, Round>,
+
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump = user_contribution.bump
+    )]
+    pub user_contribution: Account<'info, UserContribution>,
+
+    #[account(
+        mut,
+        seeds = [SOL_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = sol_vault.bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_usdt_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required when the user contributed USDC
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_usdc_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the user contributed USDT
+    #[account(
+        mut,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_usdt_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = USDC_MINT)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = USDT_MINT)]
+    pub usdt_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn handler(ctx: Context<Refund>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    require!(
+        presale_config.refunds_available(&ctx.accounts.final_round, Clock::get()?.unix_timestamp),
+        ErrorCode::RefundsNotAvailable
+    );
+
+    let user_contribution = &mut ctx.accounts.user_contribution;
+    let sol_lamports = user_contribution.contributed_sol_lamports;
+    let usdc_amount = user_contribution.contributed_usdc;
+    let usdt_amount = user_contribution.contributed_usdt;
+
+    require!(
+        sol_lamports > 0 || usdc_amount > 0 || usdt_amount > 0,
+        ErrorCode::NoContributionsToRefund
+    );
+
+    // Purchases paid with accepted mints stay until refund_token returns their escrow, and the
+    // quote nonce is kept so earlier signed quotes cannot be replayed
+    let direct_purchases = user_contribution.direct_purchases()?;
+    user_contribution.remove_purchases(&direct_purchases)?;
+    user_contribution.contributed_sol_lamports = 0;
+    user_contribution.contributed_usdc = 0;
+    user_contribution.contributed_usdt = 0;
+    presale_config.remove_purchases(&direct_purchases)?;
+
+    let user_key = user_contribution.user;
+
+    if sol_lamports > 0 {
+        ctx.accounts.sol_vault.sub_lamports(sol_lamports)?;
+        ctx.accounts.user.add_lamports(sol_lamports)?;
+    }
+
+    let presale_id = presale_config.presale_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        PRESALE_SEED.as_bytes(),
+        presale_id.as_ref(),
+        &[presale_config.bump],
+    ]];
+
+    if usdc_amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_usdc_ata.to_account_info(),
+            to: ctx
+                .accounts
+                .user_usdc_ata
+                .as_ref()
+                .ok_or(ErrorCode::MissingUserTokenAccount)?
+                .to_account_info(),
+            authority: presale_config.to_account_info(),
+            mint: ctx.accounts.usdc_mint.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            usdc_amount,
+            ctx.accounts.usdc_mint.decimals,
+        )?;
+    }
+
+    if usdt_amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_usdt_ata.to_account_info(),
+            to: ctx
+                .accounts
+                .user_usdt_ata
+                .as_ref()
+                .ok_or(ErrorCode::MissingUserTokenAccount)?
+                .to_account_info(),
+            authority: presale_config.to_account_info(),
+            mint: ctx.accounts.usdt_mint.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            usdt_amount,
+            ctx.accounts.usdt_mint.decimals,
+        )?;
+    }
+
+    emit!(Refunded {
+        user: user_key,
+        sol_lamports,
+        usdc_amount,
+        usdt_amount,
+    });
+
+    Ok(())
+}
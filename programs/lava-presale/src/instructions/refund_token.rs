@@ -0,0 +1,137 @@
+use crate::error::ErrorCode;
+use crate::events::TokenRefunded;
+use crate::{
+    PresaleConfig, Round, TokenContribution, UserContribution, PRESALE_SEED, ROUND_SEED,
+    TOKEN_CONTRIBUTION_SEED, USER_CONTRIBUTION_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+/// Refunds the escrowed payments made in one accepted mint and drops the purchases they paid for.
+/// Transfer fees of Token-2022 mints are charged again on the way back.
+#[derive(Accounts)]
+pub struct RefundToken<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = final_round.bump
+    )]
+    pub final_round: Account<'info, Round>,
+
+    #[account(
+        mut,
+        has_one = user @ ErrorCode::Unauthorized,
+        seeds = [
+            USER_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump = user_contribution.bump
+    )]
+    pub user_contribution: Account<'info, UserContribution>,
+
+    #[account(
+        mut,
+        close = user,
+        has_one = user @ ErrorCode::Unauthorized,
+        has_one = mint @ ErrorCode::InvalidPaymentToken,
+        seeds = [
+            TOKEN_CONTRIBUTION_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            mint.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump = token_contribution.bump
+    )]
+    pub token_contribution: Account<'info, TokenContribution>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RefundToken>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    require!(
+        presale_config.refunds_available(&ctx.accounts.final_round, Clock::get()?.unix_timestamp),
+        ErrorCode::RefundsNotAvailable
+    );
+
+    let amount = ctx.accounts.token_contribution.amount;
+    require_gt!(amount, 0, ErrorCode::NoContributionsToRefund);
+
+    let purchases = ctx.accounts.token_contribution.purchases;
+    let user_contribution = &mut ctx.accounts.user_contribution;
+    user_contribution.remove_purchases(&purchases)?;
+    user_contribution.token_purchases.sub(&purchases)?;
+    presale_config.remove_purchases(&purchases)?;
+
+    let presale_id = presale_config.presale_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        PRESALE_SEED.as_bytes(),
+        presale_id.as_ref(),
+        &[presale_config.bump],
+    ]];
+
+    let transfer_accounts = TransferChecked {
+        from: ctx.accounts.escrow_ata.to_account_info(),
+        to: ctx.accounts.user_ata.to_account_info(),
+        authority: presale_config.to_account_info(),
+        mint: ctx.accounts.mint.to_account_info(),
+    };
+    transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            signer_seeds,
+        ),
+        amount,
+        ctx.accounts.mint.decimals,
+    )?;
+
+    emit!(TokenRefunded {
+        user: ctx.accounts.user.key(),
+        mint: ctx.accounts.mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::error::ErrorCode;
+use crate::events::DeployerRemoved;
+use crate::{Deployer, DEPLOYER_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RemoveDeployer<'info> {
+    #[account(mut)]
+    pub upgrade_authority: Signer<'info>,
+
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = ProgramData::owner(),
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(
+        mut,
+        close = upgrade_authority,
+        seeds = [DEPLOYER_SEED.as_bytes(), deployer_entry.deployer.as_ref()],
+        bump = deployer_entry.bump
+    )]
+    pub deployer_entry: Account<'info, Deployer>,
+}
+
+pub fn handler(ctx: Context<RemoveDeployer>) -> Result<()> {
+    emit!(DeployerRemoved {
+        deployer: ctx.accounts.deployer_entry.deployer,
+    });
+
+    Ok(())
+}
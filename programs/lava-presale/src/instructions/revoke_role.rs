@@ -0,0 +1,26 @@
+use crate::error::ErrorCode;
+use crate::events::RoleRevoked;
+use crate::{PresaleConfig, Role, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RevokeRole<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<RevokeRole>, role: Role) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    let account = std::mem::take(presale_config.role_mut(role));
+
+    emit!(RoleRevoked { role, account });
+
+    Ok(())
+}
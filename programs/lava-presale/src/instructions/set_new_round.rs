@@ -7,21 +7,35 @@ use anchor_lang::prelude::*;
 #[derive(Accounts)]
 pub struct SetNewRound<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub operator: Signer<'info>,
 
     #[account(
         mut,
-        has_one = authority @ ErrorCode::Unauthorized,
-        seeds = [PRESALE_SEED.as_bytes()],
+        has_one = operator @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump = presale_config.bump
     )]
     pub presale_config: Account<'info, PresaleConfig>,
 
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            presale_config.last_round.to_le_bytes().as_ref()
+        ],
+        bump = previous_round.bump
+    )]
+    pub previous_round: Account<'info, Round>,
+
     #[account(
         init,
-        payer = authority,
+        payer = operator,
         space = Round::DISCRIMINATOR.len() + Round::INIT_SPACE,
-        seeds = [ROUND_SEED.as_bytes(), (presale_config.current_round + 1).to_le_bytes().as_ref()],
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            (presale_config.last_round + 1).to_le_bytes().as_ref()
+        ],
         bump
     )]
     pub round: Account<'info, Round>,
@@ -37,27 +51,27 @@ pub fn handler(ctx: Context<SetNewRound>, new_round: CreateRoundData) -> Result<
         !presale_config.finalized,
         ErrorCode::PresaleAlreadyFinalized
     );
+    // Once the final round has ended the sale is over, a new round would close the refund window
     require!(
-        presale_config.current_round < (crate::constants::MAX_STAGES) as u8,
-        ErrorCode::InvalidRoundConfig
+        Clock::get()?.unix_timestamp <= ctx.accounts.previous_round.end_time,
+        ErrorCode::PresaleEnded
     );
-    require!(new_round.token_price_usd > 0, ErrorCode::InvalidRoundConfig);
     require!(
-        new_round.start_time < new_round.end_time,
+        presale_config.last_round < (crate::constants::MAX_STAGES) as u8,
         ErrorCode::InvalidRoundConfig
     );
+    new_round.validate(
+        ctx.accounts.previous_round.end_time,
+        ctx.accounts.previous_round.token_price_usd,
+    )?;
+
+    // The round is only scheduled here, buyers move into it once the previous round
+    // sells out or ends
+    let next_stage = presale_config.last_round + 1;
+    presale_config.last_round = next_stage;
+    msg!("Scheduled stage {}", next_stage);
 
-    let next_stage = presale_config.current_round + 1;
-    presale_config.current_round = next_stage;
-    msg!("Progressed to stage {}", next_stage);
-
-    stage.set_inner(Round {
-        round_id: next_stage,
-        token_price_usd: new_round.token_price_usd,
-        start_time: new_round.start_time,
-        end_time: new_round.end_time,
-        bump: ctx.bumps.round,
-    });
+    stage.set_inner(Round::new(next_stage, &new_round, ctx.bumps.round));
 
     Ok(())
 }
@@ -0,0 +1,41 @@
+use crate::error::ErrorCode;
+use crate::events::PaymentMethodsUpdated;
+use crate::{PresaleConfig, ALL_PAYMENT_METHODS, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+/// The pauser may only disable payment methods, re-enabling them is left to the authority
+#[derive(Accounts)]
+pub struct SetPaymentMethods<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = signer.key() == presale_config.pauser
+            || signer.key() == presale_config.authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<SetPaymentMethods>, enabled_payment_methods: u8) -> Result<()> {
+    require!(
+        enabled_payment_methods & !ALL_PAYMENT_METHODS == 0,
+        ErrorCode::InvalidPaymentToken
+    );
+
+    let presale_config = &mut ctx.accounts.presale_config;
+    if ctx.accounts.signer.key() != presale_config.authority {
+        require!(
+            enabled_payment_methods & !presale_config.enabled_payment_methods == 0,
+            ErrorCode::Unauthorized
+        );
+    }
+    presale_config.enabled_payment_methods = enabled_payment_methods;
+
+    emit!(PaymentMethodsUpdated {
+        enabled_payment_methods,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::error::ErrorCode;
+use crate::events::TgeTimestampUpdated;
+use crate::{PresaleConfig, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetTgeTimestamp<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<SetTgeTimestamp>, tge_timestamp: i64) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    let now = Clock::get()?.unix_timestamp;
+
+    // Once TGE has passed, claims may already have been made against the current schedule
+    require!(
+        presale_config.tge_timestamp == 0 || now < presale_config.tge_timestamp,
+        ErrorCode::VestingAlreadyStarted
+    );
+    require_gt!(tge_timestamp, 0, ErrorCode::InvalidVestingSchedule);
+
+    presale_config.tge_timestamp = tge_timestamp;
+
+    emit!(TgeTimestampUpdated { tge_timestamp });
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use crate::error::ErrorCode;
+use crate::events::TreasuryUpdated;
+use crate::{PresaleConfig, PRESALE_SEED, USDC_MINT, USDT_MINT};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = !presale_config.is_timelocked() @ ErrorCode::TimelockActive,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    /// CHECK: New treasury wallet that will receive funds
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_usdt_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = USDC_MINT)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = USDT_MINT)]
+    pub usdt_mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accepted mints keep paying their stored treasury_ata until update_accepted_mint re-derives it
+/// for the new treasury
+pub fn handler(ctx: Context<SetTreasury>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    let previous_treasury = presale_config.treasury;
+
+    presale_config.treasury = ctx.accounts.treasury.key();
+
+    emit!(TreasuryUpdated {
+        previous_treasury,
+        treasury: presale_config.treasury,
+    });
+
+    Ok(())
+}
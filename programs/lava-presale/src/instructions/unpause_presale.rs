@@ -0,0 +1,32 @@
+use crate::error::ErrorCode;
+use crate::events::PresaleUnpaused;
+use crate::{PresaleConfig, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+/// Resuming the sale is left to the authority, the pauser can only stop it
+#[derive(Accounts)]
+pub struct UnpausePresale<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+}
+
+pub fn handler(ctx: Context<UnpausePresale>) -> Result<()> {
+    let presale_config = &mut ctx.accounts.presale_config;
+    require!(presale_config.paused, ErrorCode::PresaleNotPaused);
+
+    presale_config.paused = false;
+
+    emit!(PresaleUnpaused {
+        authority: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::error::ErrorCode;
+use crate::events::AcceptedMintUpdated;
+use crate::payment_mint::check_extensions;
+use crate::{AcceptedMint, AcceptedMintData, PresaleConfig, ACCEPTED_MINT_SEED, PRESALE_SEED};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct UpdateAcceptedMint<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Treasury wallet that receives the payments
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        has_one = treasury @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        has_one = mint,
+        seeds = [ACCEPTED_MINT_SEED.as_bytes(), presale_config.key().as_ref(), mint.key().as_ref()],
+        bump = accepted_mint.bump
+    )]
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    /// Re-derived from the current treasury so a treasury change can be picked up
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<UpdateAcceptedMint>, data: AcceptedMintData) -> Result<()> {
+    data.validate()?;
+    check_extensions(
+        &ctx.accounts.mint.to_account_info(),
+        data.allow_permanent_delegate,
+    )?;
+
+    let accepted_mint = &mut ctx.accounts.accepted_mint;
+    require!(
+        accepted_mint.allows_direct_update(&data, ctx.accounts.presale_config.is_timelocked()),
+        ErrorCode::TimelockActive
+    );
+    accepted_mint.price_feed = data.price_feed;
+    accepted_mint.stable = data.stable;
+    accepted_mint.treasury_ata = ctx.accounts.treasury_ata.key();
+    accepted_mint.enabled = data.enabled;
+    accepted_mint.allow_permanent_delegate = data.allow_permanent_delegate;
+
+    emit!(AcceptedMintUpdated {
+        mint: accepted_mint.mint,
+        price_feed: data.price_feed,
+        stable: data.stable,
+        treasury_ata: accepted_mint.treasury_ata,
+        enabled: data.enabled,
+        allow_permanent_delegate: data.allow_permanent_delegate,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::error::ErrorCode;
+use crate::events::OracleConfigUpdated;
+use crate::{OracleConfig, OracleConfigData, PresaleConfig, ORACLE_CONFIG_SEED, PRESALE_SEED};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdateOracleConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = !presale_config.is_timelocked() @ ErrorCode::TimelockActive,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        seeds = [ORACLE_CONFIG_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+}
+
+pub fn handler(ctx: Context<UpdateOracleConfig>, data: OracleConfigData) -> Result<()> {
+    data.validate()?;
+
+    let oracle_config = &mut ctx.accounts.oracle_config;
+    let bump = oracle_config.bump;
+    oracle_config.set_inner(OracleConfig::new(&data, bump));
+
+    emit!(OracleConfigUpdated {
+        feed_id: data.feed_id,
+        price_account: data.price_account,
+        max_staleness: data.max_staleness,
+        max_price_conf_bps: data.max_price_conf_bps,
+        conservative_pricing: data.conservative_pricing,
+        primary_source: data.primary_source,
+        switchboard_feed: data.switchboard_feed,
+        max_deviation_bps: data.max_deviation_bps,
+    });
+
+    Ok(())
+}
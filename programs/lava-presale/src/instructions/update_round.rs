@@ -0,0 +1,105 @@
+use crate::error::ErrorCode;
+use crate::events::RoundUpdated;
+use crate::{CreateRoundData, PresaleConfig, Round, PRESALE_SEED, ROUND_SEED, START_ROUND_ID};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(round_id: u8)]
+pub struct UpdateRound<'info> {
+    pub operator: Signer<'info>,
+
+    #[account(
+        has_one = operator @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            round_id.to_le_bytes().as_ref()
+        ],
+        bump = round.bump
+    )]
+    pub round: Account<'info, Round>,
+
+    /// Required unless the updated round is the first one
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            round_id.wrapping_sub(1).to_le_bytes().as_ref()
+        ],
+        bump = previous_round.bump
+    )]
+    pub previous_round: Option<Account<'info, Round>>,
+
+    /// Required unless the updated round is the last scheduled one
+    #[account(
+        seeds = [
+            ROUND_SEED.as_bytes(),
+            presale_config.key().as_ref(),
+            round_id.wrapping_add(1).to_le_bytes().as_ref()
+        ],
+        bump = next_round.bump
+    )]
+    pub next_round: Option<Account<'info, Round>>,
+}
+
+pub fn handler(ctx: Context<UpdateRound>, round_id: u8, data: CreateRoundData) -> Result<()> {
+    let presale_config = &ctx.accounts.presale_config;
+    require!(
+        !presale_config.finalized,
+        ErrorCode::PresaleAlreadyFinalized
+    );
+
+    let round = &mut ctx.accounts.round;
+    require!(
+        Clock::get()?.unix_timestamp < round.start_time,
+        ErrorCode::RoundAlreadyStarted
+    );
+
+    match &ctx.accounts.previous_round {
+        Some(previous_round) => {
+            data.validate(previous_round.end_time, previous_round.token_price_usd)?
+        }
+        None => {
+            require_eq!(round_id, START_ROUND_ID, ErrorCode::MissingRoundAccount);
+            data.validate(0, 0)?
+        }
+    }
+
+    match &ctx.accounts.next_round {
+        Some(next_round) => {
+            require!(
+                data.end_time <= next_round.start_time,
+                ErrorCode::InvalidRoundConfig
+            );
+            require!(
+                data.token_price_usd <= next_round.token_price_usd,
+                ErrorCode::InvalidRoundConfig
+            );
+        }
+        None => require_eq!(
+            round_id,
+            presale_config.last_round,
+            ErrorCode::MissingRoundAccount
+        ),
+    }
+
+    let bump = round.bump;
+    round.set_inner(Round::new(round_id, &data, bump));
+
+    emit!(RoundUpdated {
+        round_id,
+        token_price_usd: data.token_price_usd,
+        start_time: data.start_time,
+        end_time: data.end_time,
+        token_supply: data.token_supply,
+    });
+
+    Ok(())
+}
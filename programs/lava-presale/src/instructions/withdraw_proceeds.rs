@@ -0,0 +1,151 @@
+use crate::error::ErrorCode;
+use crate::events::ProceedsWithdrawn;
+use crate::{PresaleConfig, SolVault, PRESALE_SEED, SOL_VAULT_SEED, USDC_MINT, USDT_MINT};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    pub authority: Signer<'info>,
+
+    /// CHECK: Treasury wallet that receives the escrowed funds
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        has_one = treasury @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        seeds = [SOL_VAULT_SEED.as_bytes(), presale_config.key().as_ref()],
+        bump = sol_vault.bump
+    )]
+    pub sol_vault: Account<'info, SolVault>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_usdt_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdt_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_usdt_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = USDC_MINT)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = USDT_MINT)]
+    pub usdt_mint: InterfaceAccount<'info, Mint>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn handler(ctx: Context<WithdrawProceeds>) -> Result<()> {
+    let presale_config = &ctx.accounts.presale_config;
+    require!(presale_config.finalized, ErrorCode::PresaleNotFinalized);
+    require_gte!(
+        presale_config.total_raised_usd,
+        presale_config.soft_cap_usd,
+        ErrorCode::SoftCapNotReached
+    );
+
+    // Keep the vault rent exempt
+    let sol_vault_info = ctx.accounts.sol_vault.to_account_info();
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(sol_vault_info.data_len());
+    let sol_lamports = sol_vault_info
+        .lamports()
+        .saturating_sub(rent_exempt_lamports);
+
+    if sol_lamports > 0 {
+        ctx.accounts.sol_vault.sub_lamports(sol_lamports)?;
+        ctx.accounts.treasury.add_lamports(sol_lamports)?;
+    }
+
+    let presale_id = presale_config.presale_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        PRESALE_SEED.as_bytes(),
+        presale_id.as_ref(),
+        &[presale_config.bump],
+    ]];
+
+    let usdc_amount = ctx.accounts.escrow_usdc_ata.amount;
+    if usdc_amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_usdc_ata.to_account_info(),
+            to: ctx.accounts.treasury_usdc_ata.to_account_info(),
+            authority: presale_config.to_account_info(),
+            mint: ctx.accounts.usdc_mint.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            usdc_amount,
+            ctx.accounts.usdc_mint.decimals,
+        )?;
+    }
+
+    // USDC and USDT share a mint on devnet, so read the balance after the USDC transfer
+    ctx.accounts.escrow_usdt_ata.reload()?;
+    let usdt_amount = ctx.accounts.escrow_usdt_ata.amount;
+    if usdt_amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_usdt_ata.to_account_info(),
+            to: ctx.accounts.treasury_usdt_ata.to_account_info(),
+            authority: presale_config.to_account_info(),
+            mint: ctx.accounts.usdt_mint.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            usdt_amount,
+            ctx.accounts.usdt_mint.decimals,
+        )?;
+    }
+
+    emit!(ProceedsWithdrawn {
+        treasury: ctx.accounts.treasury.key(),
+        sol_lamports,
+        usdc_amount,
+        usdt_amount,
+    });
+
+    Ok(())
+}
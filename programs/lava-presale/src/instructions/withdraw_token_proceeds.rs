@@ -0,0 +1,90 @@
+use crate::error::ErrorCode;
+use crate::events::TokenProceedsWithdrawn;
+use crate::{AcceptedMint, PresaleConfig, ACCEPTED_MINT_SEED, PRESALE_SEED};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+/// Moves the escrowed payments of one accepted mint to its treasury ATA
+#[derive(Accounts)]
+pub struct WithdrawTokenProceeds<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized,
+        seeds = [PRESALE_SEED.as_bytes(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump = presale_config.bump
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        has_one = mint @ ErrorCode::InvalidPaymentToken,
+        has_one = treasury_ata @ ErrorCode::InvalidAcceptedMint,
+        seeds = [ACCEPTED_MINT_SEED.as_bytes(), presale_config.key().as_ref(), mint.key().as_ref()],
+        bump = accepted_mint.bump
+    )]
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = presale_config,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub treasury_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn handler(ctx: Context<WithdrawTokenProceeds>) -> Result<()> {
+    let presale_config = &ctx.accounts.presale_config;
+    require!(presale_config.finalized, ErrorCode::PresaleNotFinalized);
+    require_gte!(
+        presale_config.total_raised_usd,
+        presale_config.soft_cap_usd,
+        ErrorCode::SoftCapNotReached
+    );
+
+    let amount = ctx.accounts.escrow_ata.amount;
+    if amount > 0 {
+        let presale_id = presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_SEED.as_bytes(),
+            presale_id.as_ref(),
+            &[presale_config.bump],
+        ]];
+
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_ata.to_account_info(),
+            to: ctx.accounts.treasury_ata.to_account_info(),
+            authority: presale_config.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+        };
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+    }
+
+    emit!(TokenProceedsWithdrawn {
+        treasury_ata: ctx.accounts.treasury_ata.key(),
+        mint: ctx.accounts.mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
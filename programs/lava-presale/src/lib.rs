@@ -1,15 +1,25 @@
-#![allow(unexpected_cfgs)]
+// Anchor's cfgs are unknown to rustc, its IDL handlers (emitted at the crate root, outside
+// `lava_presale`) call the deprecated `AccountInfo::realloc`, and each instruction module exports
+// a `handler` for the glob re-exports below.
+#![allow(unexpected_cfgs, deprecated, ambiguous_glob_reexports)]
 
 pub mod constants;
 pub mod error;
 pub mod events;
 pub mod instructions;
+pub mod oracle;
+pub mod payment_mint;
+pub mod pricing;
+pub mod purchase;
+pub mod quote;
 pub mod state;
 
 use anchor_lang::prelude::*;
 
 pub use constants::*;
 pub use instructions::*;
+pub use purchase::Order;
+pub use quote::PurchaseQuote;
 pub use state::*;
 
 declare_id!("7w2jgcmfppLLfxSGi7mw7ttjtBehQBrwuFKzQUaCRE1w");
@@ -20,9 +30,20 @@ pub mod lava_presale {
 
     pub fn initialize_presale(
         ctx: Context<InitializePresale>,
+        presale_id: u64,
         first_stage: CreateRoundData,
+        soft_cap_usd: u64,
+        escrow_enabled: bool,
     ) -> Result<()> {
-        initialize_presale::handler(ctx, first_stage)
+        initialize_presale::handler(ctx, presale_id, first_stage, soft_cap_usd, escrow_enabled)
+    }
+
+    pub fn add_deployer(ctx: Context<AddDeployer>, deployer: Pubkey) -> Result<()> {
+        add_deployer::handler(ctx, deployer)
+    }
+
+    pub fn remove_deployer(ctx: Context<RemoveDeployer>) -> Result<()> {
+        remove_deployer::handler(ctx)
     }
 
     pub fn finalize_presale(ctx: Context<FinalizePresale>) -> Result<()> {
@@ -33,12 +54,81 @@ pub mod lava_presale {
         set_new_round::handler(ctx, new_round)
     }
 
+    pub fn configure_rounds<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ConfigureRounds<'info>>,
+        rounds: Vec<CreateRoundData>,
+    ) -> Result<()> {
+        configure_rounds::handler(ctx, rounds)
+    }
+
+    pub fn update_round(
+        ctx: Context<UpdateRound>,
+        round_id: u8,
+        data: CreateRoundData,
+    ) -> Result<()> {
+        update_round::handler(ctx, round_id, data)
+    }
+
+    pub fn extend_round(ctx: Context<ExtendRound>, round_id: u8, end_time: i64) -> Result<()> {
+        extend_round::handler(ctx, round_id, end_time)
+    }
+
+    pub fn cancel_round(ctx: Context<CancelRound>) -> Result<()> {
+        cancel_round::handler(ctx)
+    }
+
+    pub fn advance_round(ctx: Context<AdvanceRound>) -> Result<()> {
+        advance_round::handler(ctx)
+    }
+
     pub fn buy_with_sol(
         ctx: Context<BuyWithSol>,
         token_amount: u64,
+        max_payment: Option<u64>,
         refferal: Option<ReferralData>,
     ) -> Result<()> {
-        buy_with_sol::handler(ctx, token_amount, refferal)
+        buy_with_sol::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount,
+                max_payment,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_sol_exact_in(
+        ctx: Context<BuyWithSol>,
+        payment_lamports: u64,
+        min_tokens_out: u64,
+        refferal: Option<ReferralData>,
+    ) -> Result<()> {
+        buy_with_sol::handler(
+            ctx,
+            Order::ExactIn {
+                payment_amount: payment_lamports,
+                min_tokens_out,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_sol_quoted(
+        ctx: Context<BuyWithSol>,
+        quote: PurchaseQuote,
+        max_payment: Option<u64>,
+    ) -> Result<()> {
+        buy_with_sol::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount: quote.token_amount,
+                max_payment,
+            },
+            quote.referral.clone(),
+            Some(quote),
+        )
     }
 
     pub fn buy_with_usd(
@@ -46,6 +136,186 @@ pub mod lava_presale {
         token_amount: u64,
         refferal: Option<ReferralData>,
     ) -> Result<()> {
-        buy_with_usd::handler(ctx, token_amount, refferal)
+        buy_with_usd::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount,
+                max_payment: None,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_usd_exact_in(
+        ctx: Context<BuyWithUsd>,
+        payment_amount: u64,
+        min_tokens_out: u64,
+        refferal: Option<ReferralData>,
+    ) -> Result<()> {
+        buy_with_usd::handler(
+            ctx,
+            Order::ExactIn {
+                payment_amount,
+                min_tokens_out,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_usd_quoted(ctx: Context<BuyWithUsd>, quote: PurchaseQuote) -> Result<()> {
+        buy_with_usd::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount: quote.token_amount,
+                max_payment: None,
+            },
+            quote.referral.clone(),
+            Some(quote),
+        )
+    }
+
+    pub fn buy_with_token(
+        ctx: Context<BuyWithToken>,
+        token_amount: u64,
+        max_payment: Option<u64>,
+        refferal: Option<ReferralData>,
+    ) -> Result<()> {
+        buy_with_token::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount,
+                max_payment,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_token_exact_in(
+        ctx: Context<BuyWithToken>,
+        payment_amount: u64,
+        min_tokens_out: u64,
+        refferal: Option<ReferralData>,
+    ) -> Result<()> {
+        buy_with_token::handler(
+            ctx,
+            Order::ExactIn {
+                payment_amount,
+                min_tokens_out,
+            },
+            refferal,
+            None,
+        )
+    }
+
+    pub fn buy_with_token_quoted(
+        ctx: Context<BuyWithToken>,
+        quote: PurchaseQuote,
+        max_payment: Option<u64>,
+    ) -> Result<()> {
+        buy_with_token::handler(
+            ctx,
+            Order::ExactOut {
+                token_amount: quote.token_amount,
+                max_payment,
+            },
+            quote.referral.clone(),
+            Some(quote),
+        )
+    }
+
+    pub fn add_accepted_mint(ctx: Context<AddAcceptedMint>, data: AcceptedMintData) -> Result<()> {
+        add_accepted_mint::handler(ctx, data)
+    }
+
+    pub fn update_accepted_mint(
+        ctx: Context<UpdateAcceptedMint>,
+        data: AcceptedMintData,
+    ) -> Result<()> {
+        update_accepted_mint::handler(ctx, data)
+    }
+
+    pub fn initialize_token_vault(ctx: Context<InitializeTokenVault>) -> Result<()> {
+        initialize_token_vault::handler(ctx)
+    }
+
+    pub fn set_tge_timestamp(ctx: Context<SetTgeTimestamp>, tge_timestamp: i64) -> Result<()> {
+        set_tge_timestamp::handler(ctx, tge_timestamp)
+    }
+
+    pub fn claim_tokens(ctx: Context<ClaimTokens>) -> Result<()> {
+        claim_tokens::handler(ctx)
+    }
+
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        refund::handler(ctx)
+    }
+
+    pub fn refund_token(ctx: Context<RefundToken>) -> Result<()> {
+        refund_token::handler(ctx)
+    }
+
+    pub fn pause_presale(ctx: Context<PausePresale>) -> Result<()> {
+        pause_presale::handler(ctx)
+    }
+
+    pub fn unpause_presale(ctx: Context<UnpausePresale>) -> Result<()> {
+        unpause_presale::handler(ctx)
+    }
+
+    pub fn set_payment_methods(
+        ctx: Context<SetPaymentMethods>,
+        enabled_payment_methods: u8,
+    ) -> Result<()> {
+        set_payment_methods::handler(ctx, enabled_payment_methods)
+    }
+
+    pub fn update_oracle_config(
+        ctx: Context<UpdateOracleConfig>,
+        data: OracleConfigData,
+    ) -> Result<()> {
+        update_oracle_config::handler(ctx, data)
+    }
+
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>) -> Result<()> {
+        withdraw_proceeds::handler(ctx)
+    }
+
+    pub fn withdraw_token_proceeds(ctx: Context<WithdrawTokenProceeds>) -> Result<()> {
+        withdraw_token_proceeds::handler(ctx)
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        propose_authority::handler(ctx, new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        accept_authority::handler(ctx)
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>) -> Result<()> {
+        set_treasury::handler(ctx)
+    }
+
+    pub fn grant_role(ctx: Context<GrantRole>, role: Role, account: Pubkey) -> Result<()> {
+        grant_role::handler(ctx, role, account)
+    }
+
+    pub fn revoke_role(ctx: Context<RevokeRole>, role: Role) -> Result<()> {
+        revoke_role::handler(ctx, role)
+    }
+
+    pub fn queue_change(ctx: Context<QueueChange>, change: ConfigChange) -> Result<()> {
+        queue_change::handler(ctx, change)
+    }
+
+    pub fn execute_change(ctx: Context<ExecuteChange>) -> Result<()> {
+        execute_change::handler(ctx)
+    }
+
+    pub fn cancel_change(ctx: Context<CancelChange>) -> Result<()> {
+        cancel_change::handler(ctx)
     }
 }
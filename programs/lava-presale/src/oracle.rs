@@ -0,0 +1,372 @@
+//! SOL/USD price from a Pyth `PriceUpdateV2` account or a Switchboard On-Demand pull feed.
+//!
+//! The primary source configured in `OracleConfig` is used while it is fresh, the other one takes
+//! over when the primary is stale or not passed. When both are fresh they must agree within
+//! `max_deviation_bps`. Switchboard results are converted to Pyth's `Price` representation.
+
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use pyth_solana_receiver_sdk::price_update::{Price, PriceUpdateV2};
+
+use crate::error::ErrorCode;
+use crate::{
+    OracleConfig, OracleSource, BASIS_POINTS, MAX_PRICE_EXPONENT, MIN_PRICE_EXPONENT, USDC_DECIMALS,
+};
+
+// PullFeedAccountData layout of the Switchboard On-Demand program, offsets include the
+// 8 byte account discriminator
+const SWITCHBOARD_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+const SWITCHBOARD_FEED_LEN: usize = 8 + 3200;
+const SWITCHBOARD_LAST_UPDATE_OFFSET: usize = 2216; // last_update_timestamp: i64
+const SWITCHBOARD_VALUE_OFFSET: usize = 2264; // result.value: i128
+const SWITCHBOARD_STD_DEV_OFFSET: usize = 2280; // result.std_dev: i128
+const SWITCHBOARD_NUM_SAMPLES_OFFSET: usize = 2360; // result.num_samples: u8
+
+// Switchboard values carry 18 decimals, they are scaled down to a Pyth style exponent
+const SWITCHBOARD_DECIMALS: i32 = 18;
+const SWITCHBOARD_EXPONENT: i32 = -8;
+
+/// Fresh SOL/USD price and the source it was read from
+pub fn sol_usd_price(
+    oracle_config: &OracleConfig,
+    price_update: Option<&PriceUpdateV2>,
+    switchboard_feed: Option<&[u8]>,
+    now: i64,
+) -> Result<(Price, OracleSource)> {
+    let pyth = match price_update {
+        Some(price_update) => pyth_price(price_update, oracle_config, now)?,
+        None => None,
+    };
+    let switchboard = match switchboard_feed {
+        Some(data) => switchboard_price(data, oracle_config, now)?,
+        None => None,
+    };
+
+    let (primary, secondary, secondary_source) = match oracle_config.primary_source {
+        OracleSource::Pyth => (pyth, switchboard, OracleSource::Switchboard),
+        OracleSource::Switchboard => (switchboard, pyth, OracleSource::Pyth),
+    };
+
+    match (primary, secondary) {
+        (Some(primary), Some(secondary)) => {
+            check_deviation(&primary, &secondary, oracle_config.max_deviation_bps)?;
+            Ok((primary, oracle_config.primary_source))
+        }
+        (Some(primary), None) => Ok((primary, oracle_config.primary_source)),
+        (None, Some(secondary)) => Ok((secondary, secondary_source)),
+        (None, None) => err!(ErrorCode::OraclePriceStale),
+    }
+}
+
+/// Checks the price against the oracle guards and returns the SOL price (same exponent)
+/// purchases are converted at. Conservative pricing uses price - conf: the lower SOL price means
+/// more lamports per USD, which is the side of the confidence interval favoring the treasury.
+pub fn checked_price(price_data: &Price, oracle_config: &OracleConfig) -> Result<u64> {
+    require_gt!(price_data.price, 0, ErrorCode::NonPositiveOraclePrice);
+    require!(
+        (MIN_PRICE_EXPONENT..=MAX_PRICE_EXPONENT).contains(&price_data.exponent),
+        ErrorCode::InvalidOracleExponent
+    );
+
+    let price = price_data.price as u64;
+    // conf / price <= max_price_conf_bps / BASIS_POINTS
+    require_gte!(
+        price as u128 * oracle_config.max_price_conf_bps as u128,
+        price_data.conf as u128 * BASIS_POINTS as u128,
+        ErrorCode::OracleConfidenceTooWide
+    );
+
+    if oracle_config.conservative_pricing {
+        Ok(price - price_data.conf)
+    } else {
+        Ok(price)
+    }
+}
+
+// Conversions between USD cost (USD * 10^12) and amounts of a payment token with `decimals`
+//
+// price carries the negative oracle exponent (SOL price in USD * 10^8), so
+//
+//       total_cost_usd * 10^decimals
+// -------------------------------------------------------
+//   price * 10^|exponent| * 10^(12 - |exponent|)
+
+pub fn amount_for_usd(
+    total_cost_usd: u128,
+    price: u64,
+    exponent: i32,
+    decimals: u8,
+) -> Result<u64> {
+    let price = price as u128;
+    let convert_exponent: i32 = USDC_DECIMALS as i32 * 2 + exponent;
+    let convert_n = 10u128.pow(convert_exponent as u32);
+
+    let amount = total_cost_usd
+        .checked_mul(u64::MAX as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(
+            price
+                .checked_mul(convert_n) // 10^4 for Pyth SOL/USD
+                .ok_or(ErrorCode::ArithmeticOverflow)?,
+        )
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_mul(10u128.pow(decimals as u32))
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(u64::MAX as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+
+    Ok(amount)
+}
+
+pub fn usd_for_amount(amount: u64, price: u64, exponent: i32, decimals: u8) -> Result<u128> {
+    let price = price as u128;
+    let convert_exponent: i32 = USDC_DECIMALS as i32 * 2 + exponent;
+    let convert_n = 10u128.pow(convert_exponent as u32);
+
+    (amount as u128)
+        .checked_mul(price)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_mul(convert_n)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(10u128.pow(decimals as u32))
+        .ok_or(error!(ErrorCode::ArithmeticOverflow))
+}
+
+/// Pyth price, or None when it is older than max_staleness
+fn pyth_price(
+    price_update: &PriceUpdateV2,
+    oracle_config: &OracleConfig,
+    now: i64,
+) -> Result<Option<Price>> {
+    let clock = Clock {
+        unix_timestamp: now,
+        ..Clock::default()
+    };
+    // Also fails when the update is for a different feed or only partially verified
+    match price_update.get_price_no_older_than(
+        &clock,
+        oracle_config.max_staleness,
+        &oracle_config.feed_id,
+    ) {
+        Ok(price) => Ok(Some(price)),
+        Err(GetPriceError::PriceTooOld) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Switchboard result, or None when it is older than max_staleness
+fn switchboard_price(data: &[u8], oracle_config: &OracleConfig, now: i64) -> Result<Option<Price>> {
+    require!(
+        data.len() >= SWITCHBOARD_FEED_LEN && data[..8] == SWITCHBOARD_FEED_DISCRIMINATOR,
+        ErrorCode::InvalidOracleAccount
+    );
+
+    let last_update = i64::from_le_bytes(read(data, SWITCHBOARD_LAST_UPDATE_OFFSET));
+    let value = i128::from_le_bytes(read(data, SWITCHBOARD_VALUE_OFFSET));
+    let std_dev = i128::from_le_bytes(read(data, SWITCHBOARD_STD_DEV_OFFSET));
+    let num_samples = data[SWITCHBOARD_NUM_SAMPLES_OFFSET];
+
+    if num_samples == 0 || last_update.saturating_add(oracle_config.max_staleness as i64) < now {
+        return Ok(None);
+    }
+
+    let scale = 10i128.pow((SWITCHBOARD_DECIMALS + SWITCHBOARD_EXPONENT) as u32);
+    Ok(Some(Price {
+        price: i64::try_from(value / scale).map_err(|_| ErrorCode::InvalidOracleAccount)?,
+        conf: u64::try_from(std_dev / scale).map_err(|_| ErrorCode::InvalidOracleAccount)?,
+        exponent: SWITCHBOARD_EXPONENT,
+        publish_time: last_update,
+    }))
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N].try_into().unwrap()
+}
+
+/// |primary - secondary| / primary <= max_deviation_bps / BASIS_POINTS
+fn check_deviation(primary: &Price, secondary: &Price, max_deviation_bps: u16) -> Result<()> {
+    let primary_price = normalized(primary)?;
+    let secondary_price = normalized(secondary)?;
+    require_gt!(primary_price, 0, ErrorCode::NonPositiveOraclePrice);
+
+    require_gte!(
+        primary_price * max_deviation_bps as i128,
+        (primary_price - secondary_price).abs() * BASIS_POINTS as i128,
+        ErrorCode::OraclePriceDeviation
+    );
+
+    Ok(())
+}
+
+/// Price scaled to MIN_PRICE_EXPONENT so sources with different exponents compare
+fn normalized(price: &Price) -> Result<i128> {
+    require!(
+        (MIN_PRICE_EXPONENT..=MAX_PRICE_EXPONENT).contains(&price.exponent),
+        ErrorCode::InvalidOracleExponent
+    );
+    Ok(price.price as i128 * 10i128.pow((price.exponent - MIN_PRICE_EXPONENT) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyth_solana_receiver_sdk::price_update::{PriceFeedMessage, VerificationLevel};
+
+    const NOW: i64 = 1_700_000_000;
+    const FEED_ID: [u8; 32] = [7; 32];
+
+    fn oracle_config(primary_source: OracleSource) -> OracleConfig {
+        OracleConfig {
+            feed_id: FEED_ID,
+            price_account: Pubkey::new_unique(),
+            max_staleness: 60,
+            max_price_conf_bps: 200,
+            conservative_pricing: false,
+            primary_source,
+            switchboard_feed: Pubkey::new_unique(),
+            max_deviation_bps: 100,
+            bump: 255,
+        }
+    }
+
+    /// SOL at `price` USD with 8 decimals
+    fn pyth_update(price: i64, publish_time: i64) -> PriceUpdateV2 {
+        PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: FEED_ID,
+                price,
+                conf: price as u64 / 1_000,
+                exponent: -8,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: price,
+                ema_conf: price as u64 / 1_000,
+            },
+            posted_slot: 1,
+        }
+    }
+
+    /// SOL at `value` USD with 18 decimals
+    fn switchboard_feed(value: i128, last_update: i64) -> Vec<u8> {
+        let mut data = vec![0u8; SWITCHBOARD_FEED_LEN];
+        data[..8].copy_from_slice(&SWITCHBOARD_FEED_DISCRIMINATOR);
+        data[SWITCHBOARD_LAST_UPDATE_OFFSET..SWITCHBOARD_LAST_UPDATE_OFFSET + 8]
+            .copy_from_slice(&last_update.to_le_bytes());
+        data[SWITCHBOARD_VALUE_OFFSET..SWITCHBOARD_VALUE_OFFSET + 16]
+            .copy_from_slice(&value.to_le_bytes());
+        data[SWITCHBOARD_STD_DEV_OFFSET..SWITCHBOARD_STD_DEV_OFFSET + 16]
+            .copy_from_slice(&(value / 1_000).to_le_bytes());
+        data[SWITCHBOARD_NUM_SAMPLES_OFFSET] = 3;
+        data
+    }
+
+    const PYTH_150: i64 = 150_00000000;
+    const SWITCHBOARD_150: i128 = 150_000000000000000000;
+    const SWITCHBOARD_151: i128 = 151_000000000000000000;
+    const SWITCHBOARD_155: i128 = 155_000000000000000000;
+
+    #[test]
+    fn switchboard_result_is_converted_to_pyth_exponent() {
+        let data = switchboard_feed(SWITCHBOARD_150, NOW);
+        let price = switchboard_price(&data, &oracle_config(OracleSource::Pyth), NOW)
+            .unwrap()
+            .unwrap();
+        assert_eq!(price.price, PYTH_150);
+        assert_eq!(price.conf, PYTH_150 as u64 / 1_000);
+        assert_eq!(price.exponent, -8);
+        assert_eq!(price.publish_time, NOW);
+    }
+
+    #[test]
+    fn switchboard_feed_with_bad_discriminator_is_rejected() {
+        let mut data = switchboard_feed(SWITCHBOARD_150, NOW);
+        data[0] = 0;
+        assert!(switchboard_price(&data, &oracle_config(OracleSource::Pyth), NOW).is_err());
+        assert!(switchboard_price(&data[..100], &oracle_config(OracleSource::Pyth), NOW).is_err());
+    }
+
+    #[test]
+    fn fresh_primary_is_used() {
+        let (price, source) = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            Some(&pyth_update(PYTH_150, NOW - 10)),
+            Some(&switchboard_feed(SWITCHBOARD_151, NOW)),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(price.price, PYTH_150);
+        assert!(matches!(source, OracleSource::Pyth));
+    }
+
+    #[test]
+    fn stale_primary_falls_back_to_secondary() {
+        let (price, source) = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            Some(&pyth_update(PYTH_150, NOW - 61)),
+            Some(&switchboard_feed(SWITCHBOARD_155, NOW)),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(price.price, 155_00000000);
+        assert!(matches!(source, OracleSource::Switchboard));
+
+        let (price, source) = sol_usd_price(
+            &oracle_config(OracleSource::Switchboard),
+            Some(&pyth_update(PYTH_150, NOW)),
+            Some(&switchboard_feed(SWITCHBOARD_155, NOW - 61)),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(price.price, PYTH_150);
+        assert!(matches!(source, OracleSource::Pyth));
+    }
+
+    #[test]
+    fn missing_primary_falls_back_to_secondary() {
+        let (_, source) = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            None,
+            Some(&switchboard_feed(SWITCHBOARD_150, NOW)),
+            NOW,
+        )
+        .unwrap();
+        assert!(matches!(source, OracleSource::Switchboard));
+    }
+
+    #[test]
+    fn deviating_sources_are_rejected() {
+        let result = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            Some(&pyth_update(PYTH_150, NOW)),
+            Some(&switchboard_feed(SWITCHBOARD_155, NOW)),
+            NOW,
+        );
+        assert_eq!(result.unwrap_err(), error!(ErrorCode::OraclePriceDeviation));
+    }
+
+    #[test]
+    fn stale_sources_are_rejected() {
+        let result = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            Some(&pyth_update(PYTH_150, NOW - 61)),
+            Some(&switchboard_feed(SWITCHBOARD_150, NOW - 61)),
+            NOW,
+        );
+        assert_eq!(result.unwrap_err(), error!(ErrorCode::OraclePriceStale));
+    }
+
+    #[test]
+    fn pyth_update_for_another_feed_is_rejected() {
+        let mut price_update = pyth_update(PYTH_150, NOW);
+        price_update.price_message.feed_id = [8; 32];
+        let result = sol_usd_price(
+            &oracle_config(OracleSource::Pyth),
+            Some(&price_update),
+            None,
+            NOW,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Token-2022 handling for payment mints. Classic SPL Token mints carry no extensions and no
+//! transfer fees, so amounts pass through unchanged for them.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::transfer_hook::TransferHook;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::state::Mint;
+
+use crate::error::ErrorCode;
+
+/// Non-transferable mints cannot be paid with, and a transfer hook with a program set needs extra
+/// accounts and can block transfers. A permanent delegate can move escrowed and treasury funds at
+/// will, so such mints (PYUSD among them) are only accepted once the admin opted in.
+pub fn check_extensions(mint: &AccountInfo, allow_permanent_delegate: bool) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    for extension in mint.get_extension_types()? {
+        let supported = match extension {
+            ExtensionType::NonTransferable => false,
+            ExtensionType::TransferHook => {
+                Option::<Pubkey>::from(mint.get_extension::<TransferHook>()?.program_id).is_none()
+            }
+            ExtensionType::PermanentDelegate => allow_permanent_delegate,
+            _ => true,
+        };
+        require!(supported, ErrorCode::UnsupportedMintExtension);
+    }
+
+    Ok(())
+}
+
+/// Amount received when `amount` is sent, after any transfer fee
+pub fn net_amount(mint: &AccountInfo, amount: u64, epoch: u64) -> Result<u64> {
+    let fee = match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_epoch_fee(epoch, amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        None => 0,
+    };
+
+    Ok(amount - fee)
+}
+
+/// Amount to send so that `net_amount` is received after any transfer fee
+pub fn gross_amount(mint: &AccountInfo, net_amount: u64, epoch: u64) -> Result<u64> {
+    let fee = match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_inverse_epoch_fee(epoch, net_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        None => 0,
+    };
+
+    net_amount
+        .checked_add(fee)
+        .ok_or(error!(ErrorCode::ArithmeticOverflow))
+}
+
+fn transfer_fee_config(mint: &AccountInfo) -> Result<Option<TransferFeeConfig>> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    Ok(mint.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::extension::transfer_fee::TransferFee;
+    use spl_token_2022::extension::{
+        metadata_pointer::MetadataPointer, mint_close_authority::MintCloseAuthority,
+        non_transferable::NonTransferable, permanent_delegate::PermanentDelegate,
+    };
+    use spl_token_2022::extension::{BaseStateWithExtensionsMut, StateWithExtensionsMut};
+
+    const EPOCH: u64 = 500;
+    const FEE_BPS: u16 = 100;
+    const MAX_FEE: u64 = 5_000;
+
+    fn mint_data(
+        extension_types: &[ExtensionType],
+        init_extensions: impl FnOnce(&mut StateWithExtensionsMut<Mint>),
+    ) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<Mint>(extension_types).unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        init_extensions(&mut mint);
+        mint.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    fn fee_mint_data() -> Vec<u8> {
+        mint_data(&[ExtensionType::TransferFeeConfig], |mint| {
+            let transfer_fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: MAX_FEE.into(),
+                transfer_fee_basis_points: FEE_BPS.into(),
+            };
+            let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = transfer_fee;
+            config.newer_transfer_fee = transfer_fee;
+        })
+    }
+
+    fn with_mint<T>(owner: Pubkey, mut data: Vec<u8>, f: impl FnOnce(&AccountInfo) -> T) -> T {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mint = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        f(&mint)
+    }
+
+    #[test]
+    fn classic_mint_amounts_pass_through() {
+        with_mint(anchor_spl::token::ID, mint_data(&[], |_| {}), |mint| {
+            assert!(check_extensions(mint, false).is_ok());
+            assert_eq!(net_amount(mint, 1_000, EPOCH).unwrap(), 1_000);
+            assert_eq!(gross_amount(mint, 1_000, EPOCH).unwrap(), 1_000);
+        });
+    }
+
+    #[test]
+    fn transfer_fee_rounds_up_and_stops_at_maximum() {
+        with_mint(spl_token_2022::ID, fee_mint_data(), |mint| {
+            assert!(check_extensions(mint, false).is_ok());
+            assert_eq!(net_amount(mint, 1_000, EPOCH).unwrap(), 990);
+            // 1.5 rounds up to a fee of 2
+            assert_eq!(net_amount(mint, 150, EPOCH).unwrap(), 148);
+            assert_eq!(net_amount(mint, 10_000_000, EPOCH).unwrap(), 9_995_000);
+            assert_eq!(gross_amount(mint, 9_995_000, EPOCH).unwrap(), 10_000_000);
+        });
+    }
+
+    #[test]
+    fn gross_amount_always_delivers_the_net_amount() {
+        with_mint(spl_token_2022::ID, fee_mint_data(), |mint| {
+            for net in [1, 99, 100, 101, 149, 9_999, 494_950, 494_951, 10_000_000] {
+                let gross = gross_amount(mint, net, EPOCH).unwrap();
+                assert!(net_amount(mint, gross, EPOCH).unwrap() >= net);
+            }
+        });
+    }
+
+    fn transfer_hook_mint_data(program_id: Option<Pubkey>) -> Vec<u8> {
+        mint_data(&[ExtensionType::TransferHook], |mint| {
+            let hook = mint.init_extension::<TransferHook>(true).unwrap();
+            hook.program_id = program_id.try_into().unwrap();
+        })
+    }
+
+    /// Extensions PYUSD is initialized with, its transfer hook has no program set
+    fn pyusd_like_mint_data() -> Vec<u8> {
+        let delegate = Some(Pubkey::new_unique()).try_into().unwrap();
+        mint_data(
+            &[
+                ExtensionType::MintCloseAuthority,
+                ExtensionType::PermanentDelegate,
+                ExtensionType::TransferFeeConfig,
+                ExtensionType::TransferHook,
+                ExtensionType::MetadataPointer,
+            ],
+            |mint| {
+                mint.init_extension::<MintCloseAuthority>(true).unwrap();
+                mint.init_extension::<PermanentDelegate>(true)
+                    .unwrap()
+                    .delegate = delegate;
+                mint.init_extension::<TransferFeeConfig>(true).unwrap();
+                mint.init_extension::<TransferHook>(true).unwrap();
+                mint.init_extension::<MetadataPointer>(true).unwrap();
+            },
+        )
+    }
+
+    fn check(data: Vec<u8>, allow_permanent_delegate: bool) -> Result<()> {
+        with_mint(spl_token_2022::ID, data, |mint| {
+            check_extensions(mint, allow_permanent_delegate)
+        })
+    }
+
+    #[test]
+    fn transfer_hook_is_rejected_only_with_a_program_set() {
+        assert!(check(transfer_hook_mint_data(None), false).is_ok());
+        assert_eq!(
+            check(transfer_hook_mint_data(Some(Pubkey::new_unique())), false).unwrap_err(),
+            error!(ErrorCode::UnsupportedMintExtension)
+        );
+    }
+
+    #[test]
+    fn non_transferable_mint_is_rejected() {
+        let data = mint_data(&[ExtensionType::NonTransferable], |mint| {
+            mint.init_extension::<NonTransferable>(true).unwrap();
+        });
+        assert_eq!(
+            check(data, true).unwrap_err(),
+            error!(ErrorCode::UnsupportedMintExtension)
+        );
+    }
+
+    #[test]
+    fn permanent_delegate_requires_opt_in() {
+        assert_eq!(
+            check(pyusd_like_mint_data(), false).unwrap_err(),
+            error!(ErrorCode::UnsupportedMintExtension)
+        );
+        assert!(check(pyusd_like_mint_data(), true).is_ok());
+    }
+}
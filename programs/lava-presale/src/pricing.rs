@@ -0,0 +1,328 @@
+//! Token pricing for every `PricingStrategy`.
+//!
+//! Costs are expressed as token amount (6 decimals) times USD price (6 decimals), i.e. USD * 10^12,
+//! which keeps fixed and time based prices exact. Supply curves integrate the price over the
+//! tokens bought and round the result up, so splitting a purchase never makes it cheaper.
+
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::{PricePoint, PricingStrategy, Round, MAX_PRICE_POINTS};
+
+/// Cost of buying `token_amount` tokens from `round` at `now`, with `supply` tokens already
+/// allocated across the presale
+pub fn cost(round: &Round, token_amount: u64, supply: u64, now: i64) -> Result<u128> {
+    match supply_curve(round) {
+        Some((points, point_count)) => {
+            let end_supply = supply
+                .checked_add(token_amount)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            supply_cost(&points[..point_count], supply, end_supply)
+        }
+        None => (token_amount as u128)
+            .checked_mul(time_price(round, now)? as u128)
+            .ok_or(error!(ErrorCode::ArithmeticOverflow)),
+    }
+}
+
+/// Average price per token in USD (6 decimals) of a purchase, rounded up
+pub fn average_price(cost: u128, token_amount: u64) -> Result<u64> {
+    require_gt!(token_amount, 0);
+    u64::try_from(div_ceil(cost, token_amount as u128)?)
+        .map_err(|_| error!(ErrorCode::ArithmeticOverflow))
+}
+
+/// Spot price in USD (6 decimals) at `now` for fixed and time based strategies
+fn time_price(round: &Round, now: i64) -> Result<u64> {
+    let (end_price_usd, steps) = match round.pricing_strategy {
+        PricingStrategy::TimeLinear { end_price_usd } => (end_price_usd, None),
+        PricingStrategy::TimeStepped {
+            end_price_usd,
+            steps,
+        } => (end_price_usd, Some(steps as i128)),
+        _ => return Ok(round.token_price_usd),
+    };
+
+    let duration = (round.end_time - round.start_time) as i128;
+    let elapsed = (now.clamp(round.start_time, round.end_time) - round.start_time) as i128;
+    let price_delta = end_price_usd as i128 - round.token_price_usd as i128;
+
+    let price_change = match steps {
+        // The round is split into `steps` equal intervals, the first charges token_price_usd
+        // and the last end_price_usd
+        Some(steps) => {
+            let step = (elapsed * steps / duration).min(steps - 1);
+            price_delta * step / (steps - 1)
+        }
+        None => price_delta * elapsed / duration,
+    };
+
+    u64::try_from(round.token_price_usd as i128 + price_change)
+        .map_err(|_| error!(ErrorCode::ArithmeticOverflow))
+}
+
+/// Points of a supply based curve, starting at (start_supply, token_price_usd)
+fn supply_curve(round: &Round) -> Option<([PricePoint; MAX_PRICE_POINTS + 1], usize)> {
+    let mut curve = [PricePoint::default(); MAX_PRICE_POINTS + 1];
+
+    let (start_supply, point_count) = match round.pricing_strategy {
+        PricingStrategy::SupplyLinear {
+            start_supply,
+            end_supply,
+            end_price_usd,
+        } => {
+            curve[1] = PricePoint {
+                supply: end_supply,
+                price_usd: end_price_usd,
+            };
+            (start_supply, 1)
+        }
+        PricingStrategy::SupplyPiecewise {
+            start_supply,
+            points,
+            point_count,
+        } => {
+            let point_count = (point_count as usize).min(MAX_PRICE_POINTS);
+            curve[1..=point_count].copy_from_slice(&points[..point_count]);
+            (start_supply, point_count)
+        }
+        _ => return None,
+    };
+
+    curve[0] = PricePoint {
+        supply: start_supply,
+        price_usd: round.token_price_usd,
+    };
+
+    Some((curve, point_count + 1))
+}
+
+/// Integral of the curve between `from` and `to`. The price is flat before the first and after
+/// the last point.
+fn supply_cost(points: &[PricePoint], from: u64, to: u64) -> Result<u128> {
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let mut total: u128 = 0;
+
+    if from < first.supply {
+        let flat = (to.min(first.supply) - from) as u128;
+        total = checked_add(total, checked_mul(flat, first.price_usd as u128)?)?;
+    }
+
+    for segment in points.windows(2) {
+        let low = from.max(segment[0].supply);
+        let high = to.min(segment[1].supply);
+        if low < high {
+            total = checked_add(total, segment_cost(segment[0], segment[1], low, high)?)?;
+        }
+    }
+
+    if to > last.supply {
+        let flat = (to - from.max(last.supply)) as u128;
+        total = checked_add(total, checked_mul(flat, last.price_usd as u128)?)?;
+    }
+
+    Ok(total)
+}
+
+/// Integral between `low` and `high` of the line from `start` to `end`, rounded up:
+///
+///   (high - low) * start.price
+///     + (end.price - start.price) * (high - low) * (high + low - 2 * start.supply)
+///       / (2 * (end.supply - start.supply))
+fn segment_cost(start: PricePoint, end: PricePoint, low: u64, high: u64) -> Result<u128> {
+    let width = (high - low) as u128;
+    let base = checked_mul(width, start.price_usd as u128)?;
+
+    let rise = (end.price_usd - start.price_usd) as u128;
+    let offsets = ((high - start.supply) as u128) + ((low - start.supply) as u128);
+    let numerator = checked_mul(checked_mul(rise, width)?, offsets)?;
+    let denominator = 2 * (end.supply - start.supply) as u128;
+
+    checked_add(base, div_ceil(numerator, denominator)?)
+}
+
+fn div_ceil(numerator: u128, denominator: u128) -> Result<u128> {
+    require_gt!(denominator, 0, ErrorCode::ArithmeticOverflow);
+    Ok(numerator.div_ceil(denominator))
+}
+
+fn checked_mul(a: u128, b: u128) -> Result<u128> {
+    a.checked_mul(b)
+        .ok_or(error!(ErrorCode::ArithmeticOverflow))
+}
+
+fn checked_add(a: u128, b: u128) -> Result<u128> {
+    a.checked_add(b)
+        .ok_or(error!(ErrorCode::ArithmeticOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VestingSchedule;
+
+    const USD: u64 = 1_000_000;
+    const TOKEN: u64 = 1_000_000;
+
+    fn round(token_price_usd: u64, pricing_strategy: PricingStrategy) -> Round {
+        Round {
+            round_id: 1,
+            token_price_usd,
+            start_time: 1_000,
+            end_time: 2_000,
+            vesting: VestingSchedule::default(),
+            token_supply: 1_000_000 * TOKEN,
+            tokens_sold: 0,
+            pricing_strategy,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn fixed_cost_is_exact() {
+        let round = round(12_000, PricingStrategy::Fixed);
+        assert_eq!(
+            cost(&round, 3 * TOKEN + 1, 0, 1_500).unwrap(),
+            36_000_012_000
+        );
+    }
+
+    #[test]
+    fn time_linear_price_moves_with_clock() {
+        let round = round(
+            USD,
+            PricingStrategy::TimeLinear {
+                end_price_usd: 3 * USD,
+            },
+        );
+        assert_eq!(time_price(&round, 0).unwrap(), USD);
+        assert_eq!(time_price(&round, 1_500).unwrap(), 2 * USD);
+        assert_eq!(time_price(&round, 5_000).unwrap(), 3 * USD);
+    }
+
+    #[test]
+    fn time_stepped_price_moves_per_step() {
+        let round = round(
+            USD,
+            PricingStrategy::TimeStepped {
+                end_price_usd: 2 * USD,
+                steps: 5,
+            },
+        );
+        // Five 200 second intervals priced 1, 1.25, 1.5, 1.75 and 2 USD
+        assert_eq!(time_price(&round, 1_000).unwrap(), USD);
+        assert_eq!(time_price(&round, 1_199).unwrap(), USD);
+        assert_eq!(time_price(&round, 1_200).unwrap(), USD + USD / 4);
+        assert_eq!(time_price(&round, 1_799).unwrap(), USD + 3 * USD / 4);
+        assert_eq!(time_price(&round, 1_800).unwrap(), 2 * USD);
+        assert_eq!(time_price(&round, 1_999).unwrap(), 2 * USD);
+        assert_eq!(time_price(&round, 2_000).unwrap(), 2 * USD);
+    }
+
+    #[test]
+    fn supply_linear_integral_is_exact_on_whole_units() {
+        let round = round(
+            USD,
+            PricingStrategy::SupplyLinear {
+                start_supply: 0,
+                end_supply: TOKEN,
+                end_price_usd: 2 * USD,
+            },
+        );
+        // Average price of 1.5 USD over the whole segment
+        assert_eq!(cost(&round, TOKEN, 0, 0).unwrap(), 1_500_000_000_000);
+    }
+
+    #[test]
+    fn supply_cost_rounds_up() {
+        let round = round(
+            1,
+            PricingStrategy::SupplyLinear {
+                start_supply: 0,
+                end_supply: 3,
+                end_price_usd: 2,
+            },
+        );
+        // Exact integral over [0, 1] is 1 + 1/6
+        assert_eq!(cost(&round, 1, 0, 0).unwrap(), 2);
+        // Exact integral over [0, 3] is 4.5
+        assert_eq!(cost(&round, 3, 0, 0).unwrap(), 5);
+    }
+
+    #[test]
+    fn splitting_a_purchase_never_costs_less() {
+        let round = round(
+            7,
+            PricingStrategy::SupplyLinear {
+                start_supply: 10,
+                end_supply: 1_010,
+                end_price_usd: 13,
+            },
+        );
+        let whole = cost(&round, 900, 50, 0).unwrap();
+        let mut split = 0;
+        for index in 0..9 {
+            split += cost(&round, 100, 50 + index * 100, 0).unwrap();
+        }
+        assert!(split >= whole);
+        assert!(split - whole < 9);
+    }
+
+    #[test]
+    fn supply_price_is_flat_outside_the_curve() {
+        let round = round(
+            USD,
+            PricingStrategy::SupplyLinear {
+                start_supply: TOKEN,
+                end_supply: 2 * TOKEN,
+                end_price_usd: 3 * USD,
+            },
+        );
+        assert_eq!(
+            cost(&round, TOKEN, 0, 0).unwrap(),
+            TOKEN as u128 * USD as u128
+        );
+        assert_eq!(
+            cost(&round, TOKEN, 2 * TOKEN, 0).unwrap(),
+            TOKEN as u128 * 3 * USD as u128
+        );
+    }
+
+    #[test]
+    fn supply_piecewise_sums_segments() {
+        let mut points = [PricePoint::default(); MAX_PRICE_POINTS];
+        points[0] = PricePoint {
+            supply: TOKEN,
+            price_usd: 2 * USD,
+        };
+        points[1] = PricePoint {
+            supply: 2 * TOKEN,
+            price_usd: 2 * USD,
+        };
+        points[2] = PricePoint {
+            supply: 3 * TOKEN,
+            price_usd: 4 * USD,
+        };
+        let round = round(
+            USD,
+            PricingStrategy::SupplyPiecewise {
+                start_supply: 0,
+                points,
+                point_count: 3,
+            },
+        );
+        // 1.5 + 2 + 3 USD per token across the three segments
+        assert_eq!(cost(&round, 3 * TOKEN, 0, 0).unwrap(), 6_500_000_000_000);
+        assert_eq!(
+            cost(&round, TOKEN, 0, 0).unwrap() + cost(&round, 2 * TOKEN, TOKEN, 0).unwrap(),
+            6_500_000_000_000
+        );
+    }
+
+    #[test]
+    fn average_price_rounds_up() {
+        assert_eq!(average_price(10, 3).unwrap(), 4);
+        assert_eq!(average_price(9, 3).unwrap(), 3);
+    }
+}
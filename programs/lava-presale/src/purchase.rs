@@ -0,0 +1,450 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::RoundAdvanced;
+use crate::pricing;
+use crate::{PresaleConfig, Round, BASIS_POINTS};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RoundFill {
+    pub round_id: u8,
+    pub amount_tokens: u64,
+    pub amount_referral_bonus_tokens: u64,
+    pub token_price_usd: u64, // Average price applied to the tokens of this fill (6 decimals)
+}
+
+/// What the buyer fixes when purchasing
+#[derive(Clone, Copy)]
+pub enum Order {
+    /// Buy exactly `token_amount` tokens, paying no more than `max_payment` when set
+    ExactOut {
+        token_amount: u64,
+        max_payment: Option<u64>,
+    },
+    /// Spend exactly `payment_amount` and receive at least `min_tokens_out` tokens
+    ExactIn {
+        payment_amount: u64,
+        min_tokens_out: u64,
+    },
+}
+
+#[derive(Default, Debug)]
+pub struct Allocation {
+    pub fills: Vec<RoundFill>,
+    pub token_amount: u64,
+    pub bonus_tokens: u64,
+    pub total_cost_usd: u128, // Cost of the fills as computed by `pricing`, i.e. USD * 10^12
+}
+
+impl Allocation {
+    pub fn first_round_id(&self) -> u8 {
+        self.fills
+            .first()
+            .map(|fill| fill.round_id)
+            .unwrap_or_default()
+    }
+}
+
+/// A round is left behind once it is sold out or its end_time has passed
+pub fn should_advance(round: &Round, now: i64) -> bool {
+    round.is_sold_out() || now > round.end_time
+}
+
+/// Moves `current_round` to `next_round`. When the active round sold out before its end_time,
+/// the next round opens immediately instead of waiting for its scheduled start_time.
+pub fn advance_round(
+    presale_config: &mut PresaleConfig,
+    active_round: &Round,
+    next_round: &mut Round,
+    now: i64,
+) -> Result<()> {
+    require!(
+        should_advance(active_round, now),
+        ErrorCode::InvalidRoundConfig
+    );
+    require_eq!(
+        next_round.round_id,
+        active_round.round_id + 1,
+        ErrorCode::InvalidRoundConfig
+    );
+
+    if active_round.is_sold_out() && next_round.start_time > now {
+        next_round.start_time = now;
+    }
+    presale_config.current_round = next_round.round_id;
+
+    emit!(RoundAdvanced {
+        from_round_id: active_round.round_id,
+        to_round_id: next_round.round_id,
+        sold_out: active_round.is_sold_out(),
+    });
+
+    Ok(())
+}
+
+/// Works out how `token_amount` splits across the active round and `next_round` without
+/// touching any account. Any amount the active round cannot cover rolls into `next_round` at that
+/// round's price.
+pub fn plan(
+    presale_config: &PresaleConfig,
+    active_round: &Round,
+    next_round: Option<&Round>,
+    token_amount: u64,
+    bonus_bps: u16,
+    now: i64,
+) -> Result<Allocation> {
+    let mut allocation = Allocation::default();
+    let allocated_tokens = presale_config.total_allocated_tokens;
+
+    let remaining = if should_advance(active_round, now) {
+        let next_round = next_round.ok_or(ErrorCode::RoundNotActive)?;
+        let next_round = opened(next_round, active_round.is_sold_out(), now);
+        fill(
+            &mut allocation,
+            &next_round,
+            token_amount,
+            allocated_tokens,
+            bonus_bps,
+            now,
+        )?
+    } else {
+        let remaining = fill(
+            &mut allocation,
+            active_round,
+            token_amount,
+            allocated_tokens,
+            bonus_bps,
+            now,
+        )?;
+        match next_round {
+            // Anything left over means this purchase sells out the active round
+            Some(next_round) if remaining > 0 => fill(
+                &mut allocation,
+                &opened(next_round, true, now),
+                remaining,
+                allocated_tokens,
+                bonus_bps,
+                now,
+            )?,
+            _ => remaining,
+        }
+    };
+
+    require_eq!(remaining, 0, ErrorCode::StageSupplyExhausted);
+
+    Ok(allocation)
+}
+
+/// Allocates `token_amount` like `plan`, recording the sold tokens on the rounds and advancing to
+/// `next_round` when the purchase reaches it
+pub fn allocate(
+    presale_config: &mut PresaleConfig,
+    active_round: &mut Round,
+    next_round: Option<&mut Round>,
+    token_amount: u64,
+    bonus_bps: u16,
+    now: i64,
+) -> Result<Allocation> {
+    let allocation = plan(
+        presale_config,
+        active_round,
+        next_round.as_deref(),
+        token_amount,
+        bonus_bps,
+        now,
+    )?;
+
+    let mut fills = allocation.fills.iter().peekable();
+    if let Some(active_fill) = fills.next_if(|fill| fill.round_id == active_round.round_id) {
+        active_round.tokens_sold = active_round
+            .tokens_sold
+            .checked_add(active_fill.amount_tokens)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+    if let Some(next_fill) = fills.next() {
+        let next_round = next_round.ok_or(ErrorCode::RoundNotActive)?;
+        advance_round(presale_config, active_round, next_round, now)?;
+        next_round.tokens_sold = next_round
+            .tokens_sold
+            .checked_add(next_fill.amount_tokens)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+
+    Ok(allocation)
+}
+
+/// Largest token amount whose cost fits in `budget_usd` (USD * 10^12). Supply based prices have
+/// no closed form inverse, so this binary searches over `plan`.
+pub fn max_tokens_for_budget(
+    presale_config: &PresaleConfig,
+    active_round: &Round,
+    next_round: Option<&Round>,
+    budget_usd: u128,
+    bonus_bps: u16,
+    now: i64,
+) -> Result<u64> {
+    // Surface round errors such as RoundNotActive instead of quoting zero tokens
+    plan(presale_config, active_round, next_round, 1, bonus_bps, now)?;
+
+    let mut low = 0;
+    let mut high = active_round
+        .remaining_supply()
+        .saturating_add(next_round.map_or(0, Round::remaining_supply));
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        match plan(
+            presale_config,
+            active_round,
+            next_round,
+            mid,
+            bonus_bps,
+            now,
+        ) {
+            Ok(allocation) if allocation.total_cost_usd <= budget_usd => low = mid,
+            _ => high = mid - 1,
+        }
+    }
+
+    Ok(low)
+}
+
+/// `next_round` as it is once `advance_round` moved to it
+fn opened(next_round: &Round, active_sold_out: bool, now: i64) -> Round {
+    let mut next_round = next_round.clone();
+    if active_sold_out && next_round.start_time > now {
+        next_round.start_time = now;
+    }
+    next_round
+}
+
+fn fill(
+    allocation: &mut Allocation,
+    round: &Round,
+    remaining: u64,
+    allocated_tokens: u64,
+    bonus_bps: u16,
+    now: i64,
+) -> Result<u64> {
+    require!(round.is_active(now), ErrorCode::RoundNotActive);
+
+    let amount_tokens = remaining.min(round.remaining_supply());
+    if amount_tokens == 0 {
+        return Ok(remaining);
+    }
+
+    // Supply based strategies price against everything allocated so far, including the
+    // earlier fills of this purchase
+    let supply = allocated_tokens + allocation.token_amount + allocation.bonus_tokens;
+    let cost_usd = pricing::cost(round, amount_tokens, supply, now)?;
+    let token_price_usd = pricing::average_price(cost_usd, amount_tokens)?;
+    let bonus_tokens = amount_tokens
+        .checked_mul(bonus_bps as u64)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(BASIS_POINTS as u64)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    allocation.token_amount += amount_tokens;
+    allocation.bonus_tokens += bonus_tokens;
+    allocation.total_cost_usd = allocation
+        .total_cost_usd
+        .checked_add(cost_usd)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    allocation.fills.push(RoundFill {
+        round_id: round.round_id,
+        amount_tokens,
+        amount_referral_bonus_tokens: bonus_tokens,
+        token_price_usd,
+    });
+
+    Ok(remaining - amount_tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PricingStrategy, VestingSchedule};
+
+    const TOKEN: u64 = 1_000_000;
+    const NOW: i64 = 1_500;
+
+    fn presale_config() -> PresaleConfig {
+        PresaleConfig {
+            presale_id: 0,
+            authority: Pubkey::default(),
+            pending_authority: Pubkey::default(),
+            treasury: Pubkey::default(),
+            token_mint: Pubkey::default(),
+            current_round: 1,
+            last_round: 2,
+            finalized: false,
+            total_allocated_tokens: 0,
+            tge_timestamp: 0,
+            soft_cap_usd: 0,
+            total_raised_usd: 0,
+            escrow_enabled: false,
+            paused: false,
+            enabled_payment_methods: 0,
+            operator: Pubkey::default(),
+            pauser: Pubkey::default(),
+            cosigner: Pubkey::default(),
+            timelock_delay: 0,
+            next_change_id: 0,
+            bump: 0,
+        }
+    }
+
+    fn round(round_id: u8, token_price_usd: u64, start_time: i64, end_time: i64) -> Round {
+        Round {
+            round_id,
+            token_price_usd,
+            start_time,
+            end_time,
+            vesting: VestingSchedule::default(),
+            token_supply: 10 * TOKEN,
+            tokens_sold: 0,
+            pricing_strategy: PricingStrategy::Fixed,
+            bump: 255,
+        }
+    }
+
+    fn first_round() -> Round {
+        round(1, 100_000, 1_000, 2_000)
+    }
+
+    fn second_round() -> Round {
+        round(2, 200_000, 2_001, 3_000)
+    }
+
+    #[test]
+    fn overflow_rolls_into_next_round_at_its_price() {
+        let mut presale_config = presale_config();
+        let mut active_round = first_round();
+        active_round.tokens_sold = 8 * TOKEN;
+        let mut next_round = second_round();
+
+        let allocation = allocate(
+            &mut presale_config,
+            &mut active_round,
+            Some(&mut next_round),
+            5 * TOKEN,
+            0,
+            NOW,
+        )
+        .unwrap();
+
+        assert_eq!(allocation.fills.len(), 2);
+        assert_eq!(allocation.fills[0].round_id, 1);
+        assert_eq!(allocation.fills[0].amount_tokens, 2 * TOKEN);
+        assert_eq!(allocation.fills[0].token_price_usd, 100_000);
+        assert_eq!(allocation.fills[1].round_id, 2);
+        assert_eq!(allocation.fills[1].amount_tokens, 3 * TOKEN);
+        assert_eq!(allocation.fills[1].token_price_usd, 200_000);
+        assert_eq!(allocation.total_cost_usd, 800_000_000_000);
+
+        assert!(active_round.is_sold_out());
+        assert_eq!(next_round.tokens_sold, 3 * TOKEN);
+        assert_eq!(next_round.start_time, NOW);
+        assert_eq!(presale_config.current_round, 2);
+    }
+
+    #[test]
+    fn sold_out_round_opens_next_round_early() {
+        let mut presale_config = presale_config();
+        let mut active_round = first_round();
+        active_round.tokens_sold = active_round.token_supply;
+        let mut next_round = second_round();
+
+        let allocation = allocate(
+            &mut presale_config,
+            &mut active_round,
+            Some(&mut next_round),
+            TOKEN,
+            0,
+            NOW,
+        )
+        .unwrap();
+
+        assert_eq!(allocation.first_round_id(), 2);
+        assert_eq!(next_round.start_time, NOW);
+        assert_eq!(next_round.tokens_sold, TOKEN);
+        assert_eq!(presale_config.current_round, 2);
+    }
+
+    #[test]
+    fn expired_round_advances_on_schedule() {
+        let mut presale_config = presale_config();
+        let mut active_round = first_round();
+        let mut next_round = second_round();
+
+        let allocation = allocate(
+            &mut presale_config,
+            &mut active_round,
+            Some(&mut next_round),
+            TOKEN,
+            0,
+            2_500,
+        )
+        .unwrap();
+
+        assert_eq!(allocation.first_round_id(), 2);
+        assert_eq!(active_round.tokens_sold, 0);
+        assert_eq!(next_round.start_time, 2_001);
+        assert_eq!(presale_config.current_round, 2);
+
+        // Nothing is on sale between an expired round and a later scheduled one
+        assert_eq!(
+            plan(
+                &presale_config,
+                &first_round(),
+                Some(&round(2, 200_000, 2_600, 3_000)),
+                TOKEN,
+                0,
+                2_500,
+            )
+            .unwrap_err(),
+            error!(ErrorCode::RoundNotActive)
+        );
+    }
+
+    #[test]
+    fn last_round_stops_at_its_supply() {
+        let presale_config = presale_config();
+        let mut last_round = second_round();
+        last_round.tokens_sold = 8 * TOKEN;
+
+        assert_eq!(
+            plan(&presale_config, &last_round, None, 3 * TOKEN, 0, 2_500).unwrap_err(),
+            error!(ErrorCode::StageSupplyExhausted)
+        );
+        assert!(plan(&presale_config, &last_round, None, 2 * TOKEN, 0, 2_500).is_ok());
+    }
+
+    #[test]
+    fn budget_search_lands_on_the_exact_boundary() {
+        let presale_config = presale_config();
+        let mut active_round = first_round();
+        active_round.tokens_sold = 8 * TOKEN;
+        let next_round = second_round();
+
+        // 2 tokens at 0.1 USD plus 3 tokens at 0.2 USD
+        let budget = 800_000_000_000;
+        let max_tokens = |budget| {
+            max_tokens_for_budget(
+                &presale_config,
+                &active_round,
+                Some(&next_round),
+                budget,
+                0,
+                NOW,
+            )
+            .unwrap()
+        };
+        assert_eq!(max_tokens(budget), 5 * TOKEN);
+        assert_eq!(max_tokens(budget - 1), 5 * TOKEN - 1);
+        assert_eq!(max_tokens(budget + 199_999), 5 * TOKEN);
+        assert_eq!(max_tokens(budget + 200_000), 5 * TOKEN + 1);
+
+        // Capped by the supply left in both rounds
+        assert_eq!(max_tokens(u128::MAX), 12 * TOKEN);
+    }
+}
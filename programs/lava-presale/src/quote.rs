@@ -0,0 +1,175 @@
+//! Purchase quotes signed off-chain by the cosigner role.
+//!
+//! Instead of co-signing the transaction, the backend signs a Borsh serialized `PurchaseQuote`.
+//! The transaction verifies that signature with an ed25519 program instruction placed right before
+//! the buy instruction, which finds it through the instructions sysvar.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::error::ErrorCode;
+use crate::pricing;
+use crate::{ReferralData, UserContribution};
+
+// Ed25519SignatureOffsets follow the signature count and a padding byte
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_PUBKEY_LEN: usize = 32;
+// Instruction index the ed25519 program reads as "this instruction"
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug)]
+pub struct PurchaseQuote {
+    pub presale_config: Pubkey,
+    pub user: Pubkey,
+    pub token_amount: u64,
+    pub referral: Option<ReferralData>,
+    pub max_price_usd: u64, // Highest average price per token in USD (6 decimals)
+    pub expiry: i64,
+    pub nonce: u64, // Must exceed UserContribution.quote_nonce
+}
+
+/// Checks that `cosigner` signed `quote` for this purchase and consumes the quote's nonce
+pub fn verify(
+    quote: &PurchaseQuote,
+    presale_config: Pubkey,
+    cosigner: Pubkey,
+    token_amount: u64,
+    user_contribution: &mut UserContribution,
+    instructions_sysvar: &AccountInfo,
+    now: i64,
+) -> Result<()> {
+    require_keys_eq!(
+        quote.presale_config,
+        presale_config,
+        ErrorCode::InvalidQuote
+    );
+    require_keys_eq!(quote.user, user_contribution.user, ErrorCode::InvalidQuote);
+    require_eq!(quote.token_amount, token_amount, ErrorCode::InvalidQuote);
+    require_gte!(quote.expiry, now, ErrorCode::QuoteExpired);
+    require_gt!(
+        quote.nonce,
+        user_contribution.quote_nonce,
+        ErrorCode::QuoteNonceUsed
+    );
+
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require_gt!(current_index, 0, ErrorCode::InvalidQuoteSignature);
+    let ed25519_ix = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    require_keys_eq!(
+        ed25519_ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::InvalidQuoteSignature
+    );
+
+    let (signer, message) =
+        signed_message(&ed25519_ix.data).ok_or(ErrorCode::InvalidQuoteSignature)?;
+    require!(
+        signer == cosigner.as_ref() && message == quote.try_to_vec()?,
+        ErrorCode::InvalidQuoteSignature
+    );
+
+    user_contribution.quote_nonce = quote.nonce;
+
+    Ok(())
+}
+
+/// Rejects purchases whose average price is above the quoted one
+pub fn check_price(quote: &PurchaseQuote, total_cost_usd: u128, token_amount: u64) -> Result<()> {
+    require_gte!(
+        quote.max_price_usd,
+        pricing::average_price(total_cost_usd, token_amount)?,
+        ErrorCode::QuotePriceExceeded
+    );
+
+    Ok(())
+}
+
+/// Public key and message of an ed25519 program instruction verifying a single signature whose
+/// data is all inline
+fn signed_message(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let offsets = data.get(ED25519_OFFSETS_START..ED25519_OFFSETS_START + ED25519_OFFSETS_LEN)?;
+    if data[0] != 1 {
+        return None;
+    }
+
+    let offsets: Vec<u16> = offsets
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    let [_, signature_ix, pubkey_offset, pubkey_ix, message_offset, message_size, message_ix] =
+        offsets[..]
+    else {
+        return None;
+    };
+    if [signature_ix, pubkey_ix, message_ix]
+        .iter()
+        .any(|index| *index != CURRENT_INSTRUCTION)
+    {
+        return None;
+    }
+
+    let pubkey = data.get(pubkey_offset as usize..pubkey_offset as usize + ED25519_PUBKEY_LEN)?;
+    let message =
+        data.get(message_offset as usize..message_offset as usize + message_size as usize)?;
+
+    Some((pubkey, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Layout written by the ed25519 program's new_ed25519_instruction: offsets, public key,
+    // signature, message
+    fn ed25519_data(pubkey: &[u8; 32], message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let pubkey_offset = (ED25519_OFFSETS_START + ED25519_OFFSETS_LEN) as u16;
+        let signature_offset = pubkey_offset + 32;
+        let message_offset = signature_offset + 64;
+
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset,
+            instruction_index,
+            pubkey_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(pubkey);
+        data.extend_from_slice(&[7; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn reads_inline_pubkey_and_message() {
+        let data = ed25519_data(&[3; 32], b"quote", CURRENT_INSTRUCTION);
+        let (pubkey, message) = signed_message(&data).unwrap();
+        assert_eq!(pubkey, &[3; 32]);
+        assert_eq!(message, b"quote");
+    }
+
+    #[test]
+    fn rejects_data_from_other_instructions() {
+        let data = ed25519_data(&[3; 32], b"quote", 0);
+        assert!(signed_message(&data).is_none());
+    }
+
+    #[test]
+    fn rejects_multiple_or_truncated_signatures() {
+        let mut data = ed25519_data(&[3; 32], b"quote", CURRENT_INSTRUCTION);
+        data[0] = 2;
+        assert!(signed_message(&data).is_none());
+        data[0] = 1;
+        data.truncate(data.len() - 1);
+        assert!(signed_message(&data).is_none());
+        assert!(signed_message(&[1, 0]).is_none());
+    }
+}
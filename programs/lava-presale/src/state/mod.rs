@@ -1,21 +1,224 @@
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
+use crate::purchase::RoundFill;
+use crate::{
+    BASIS_POINTS, MAX_PRICE_POINTS, MAX_STAGES, MAX_TIMELOCK_DELAY, MIN_ESCROW_SOFT_CAP_DELAY,
+};
+
 #[account]
 #[derive(InitSpace)]
 pub struct PresaleConfig {
-    pub authority: Pubkey,
+    pub presale_id: u64,           // Distinguishes presales run by the same program
+    pub authority: Pubkey,         // Admin, grants the other roles
+    pub pending_authority: Pubkey, // Proposed by propose_authority, default when none
     pub treasury: Pubkey,
+    pub token_mint: Pubkey, // LAVA mint, set once the token vault is initialized
     pub current_round: u8,
+    pub last_round: u8, // Highest scheduled round id
     pub finalized: bool,
     pub total_allocated_tokens: u64,
+    pub tge_timestamp: i64,    // 0 until the TGE timestamp is set
+    pub soft_cap_usd: u64,     // Minimum raise in USD (6 decimals), escrowed sales refund below it
+    pub total_raised_usd: u64, // Total raised in USD (6 decimals)
+    pub escrow_enabled: bool,  // Hold proceeds in program vaults until withdrawn after finalization
+    pub paused: bool,
+    pub enabled_payment_methods: u8, // Bitmask of PAYMENT_METHOD_* flags
+    pub operator: Pubkey,            // Schedules and edits rounds
+    pub pauser: Pubkey,              // Pauses and disables payment methods, authority undoes
+    pub cosigner: Pubkey,            // Co-signs every purchase
+    pub timelock_delay: i64,         // Seconds a queued change waits, 0 applies changes directly
+    pub next_change_id: u64,         // Id of the next PendingChange
+    pub bump: u8,
+}
+
+/// Keys the admin grants on `PresaleConfig`. A revoked role is held by nobody until granted again.
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Operator,
+    Pauser,
+    Cosigner,
+}
+
+/// SOL/USD price source and the guards applied to it
+#[account]
+#[derive(InitSpace)]
+pub struct OracleConfig {
+    pub feed_id: [u8; 32],            // Pyth price feed id
+    pub price_account: Pubkey,        // PriceUpdateV2 account holding the feed
+    pub max_staleness: u64,           // Maximum price age in seconds
+    pub max_price_conf_bps: u16,      // Widest conf / price ratio accepted
+    pub conservative_pricing: bool,   // Convert SOL purchases at the lower bound price - conf
+    pub primary_source: OracleSource, // Used while fresh, the other source is the fallback
+    pub switchboard_feed: Pubkey,     // Switchboard On-Demand pull feed, default when unused
+    pub max_deviation_bps: u16,       // Widest gap accepted between two fresh sources
     pub bump: u8,
 }
 
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug)]
+pub struct OracleConfigData {
+    pub feed_id: [u8; 32],
+    pub price_account: Pubkey,
+    pub max_staleness: u64,
+    pub max_price_conf_bps: u16,
+    pub conservative_pricing: bool,
+    pub primary_source: OracleSource,
+    pub switchboard_feed: Pubkey,
+    pub max_deviation_bps: u16,
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleSource {
+    Pyth,
+    Switchboard,
+}
+
+/// Pyth feed pricing a payment token in USD
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PythFeed {
+    pub feed_id: [u8; 32],
+    pub price_account: Pubkey, // PriceUpdateV2 account holding the feed
+}
+
+/// SPL token accepted by buy_with_token
+#[account]
+#[derive(InitSpace)]
+pub struct AcceptedMint {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub price_feed: Option<PythFeed>, // Required unless the token is a USD stablecoin
+    pub stable: bool,                 // Priced 1:1 with USD
+    pub treasury_ata: Pubkey,         // Receives the payments
+    pub enabled: bool,
+    pub allow_permanent_delegate: bool, // Admin accepted that the mint's delegate can move funds
+    pub bump: u8,
+}
+
+/// Escrowed payments of a user in one accepted mint, refunded by refund_token
+#[account]
+#[derive(InitSpace)]
+pub struct TokenContribution {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,          // Received by the escrow, net of transfer fees
+    pub purchases: Purchases, // Share of the user's purchases paid with this mint
+    pub bump: u8,
+}
+
+/// Part of a user's purchases, kept per payment source so a refund removes exactly what it paid
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, Default)]
+pub struct Purchases {
+    pub contributed_usd: u64,                // USD (6 decimals)
+    pub tokens_per_round: [u64; MAX_STAGES], // Indexed by round_id - 1, including referral bonuses
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug)]
+pub struct AcceptedMintData {
+    pub price_feed: Option<PythFeed>,
+    pub stable: bool,
+    pub enabled: bool,
+    pub allow_permanent_delegate: bool,
+}
+
+/// Sensitive configuration change waiting out `PresaleConfig.timelock_delay`
+#[account]
+#[derive(InitSpace)]
+pub struct PendingChange {
+    pub change_id: u64,
+    pub change: ConfigChange,
+    pub queued_at: i64,
+    pub effective_at: i64, // execute_change is accepted from this timestamp on
+    pub bump: u8,
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug)]
+pub enum ConfigChange {
+    /// The new treasury needs its USDC and USDT ATAs by the time the change is executed
+    Treasury {
+        treasury: Pubkey,
+    },
+    SoftCap {
+        soft_cap_usd: u64,
+    },
+    OracleConfig {
+        data: OracleConfigData,
+    },
+    /// Applies to a mint registered with add_accepted_mint
+    AcceptedMint {
+        mint: Pubkey,
+        data: AcceptedMintData,
+    },
+    TimelockDelay {
+        timelock_delay: i64,
+    },
+}
+
+/// Allowlist entry letting its deployer initialize presales besides the upgrade authority
+#[account]
+#[derive(InitSpace)]
+pub struct Deployer {
+    pub deployer: Pubkey,
+    pub bump: u8,
+}
+
+/// Program-owned account escrowing the SOL paid for purchases
+#[account]
+#[derive(InitSpace)]
+pub struct SolVault {
+    pub bump: u8,
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, Default)]
+pub struct VestingSchedule {
+    pub tge_unlock_bps: u16, // Share of purchased tokens unlocked at TGE
+    pub cliff_seconds: i64,  // Delay after TGE before linear vesting starts
+    pub vesting_duration: i64,
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, Default)]
+pub struct PricePoint {
+    pub supply: u64,    // PresaleConfig.total_allocated_tokens at this point (6 decimals)
+    pub price_usd: u64, // Price per token in USD (6 decimals)
+}
+
+/// How a round prices its tokens, starting from `token_price_usd`. Time strategies move the
+/// price between start_time and end_time, supply strategies move it with
+/// `PresaleConfig.total_allocated_tokens`. See the `pricing` module.
+#[derive(AnchorDeserialize, AnchorSerialize, InitSpace, Clone, Copy, Debug, Default)]
+pub enum PricingStrategy {
+    #[default]
+    Fixed,
+    TimeLinear {
+        end_price_usd: u64,
+    },
+    /// Round split into `steps` equal intervals, priced from token_price_usd in the first to
+    /// end_price_usd in the last
+    TimeStepped {
+        end_price_usd: u64,
+        steps: u16,
+    },
+    /// token_price_usd up to start_supply, rising linearly to end_price_usd at end_supply
+    SupplyLinear {
+        start_supply: u64,
+        end_supply: u64,
+        end_price_usd: u64,
+    },
+    /// token_price_usd up to start_supply, then linear between the first `point_count` points
+    SupplyPiecewise {
+        start_supply: u64,
+        points: [PricePoint; MAX_PRICE_POINTS],
+        point_count: u8,
+    },
+}
+
 #[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug)]
 pub struct CreateRoundData {
     pub token_price_usd: u64, // Price per token in USD (6 decimals)
     pub start_time: i64,
     pub end_time: i64,
+    pub vesting: VestingSchedule,
+    pub token_supply: u64, // Tokens available in the round (6 decimals)
+    pub pricing_strategy: PricingStrategy,
 }
 
 #[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug)]
@@ -32,6 +235,10 @@ pub struct Round {
     pub token_price_usd: u64, // Price per token in USD (6 decimals)
     pub start_time: i64,
     pub end_time: i64,
+    pub vesting: VestingSchedule, // Applied to the tokens bought in this round
+    pub token_supply: u64,        // Tokens available in the round (6 decimals)
+    pub tokens_sold: u64,         // Tokens sold in the round, excluding referral bonuses
+    pub pricing_strategy: PricingStrategy,
     pub bump: u8,
 }
 
@@ -41,12 +248,837 @@ pub struct UserContribution {
     pub user: Pubkey,
     pub total_contributed_usd: u64, // Total contributed in USD (6 decimals)
     pub total_tokens_purchased: u64,
+    pub claimed_tokens: u64,
+    pub tokens_purchased_per_round: [u64; MAX_STAGES], // Indexed by round_id - 1
+    pub contributed_sol_lamports: u64,
+    pub contributed_usdc: u64,
+    pub contributed_usdt: u64,
+    pub quote_nonce: u64,           // Nonce of the last purchase quote used
+    pub token_purchases: Purchases, // Sum of the user's TokenContribution purchases
     pub bump: u8,
 }
 
+impl UserContribution {
+    pub fn new(user: Pubkey, bump: u8) -> Self {
+        Self {
+            user,
+            total_contributed_usd: 0,
+            total_tokens_purchased: 0,
+            claimed_tokens: 0,
+            tokens_purchased_per_round: [0; MAX_STAGES],
+            contributed_sol_lamports: 0,
+            contributed_usdc: 0,
+            contributed_usdt: 0,
+            quote_nonce: 0,
+            token_purchases: Purchases::default(),
+            bump,
+        }
+    }
+
+    /// Purchases paid in SOL, USDC or USDT, i.e. everything not paid with an accepted mint
+    pub fn direct_purchases(&self) -> Result<Purchases> {
+        let mut purchases = Purchases {
+            contributed_usd: self.total_contributed_usd,
+            tokens_per_round: self.tokens_purchased_per_round,
+        };
+        purchases.sub(&self.token_purchases)?;
+        Ok(purchases)
+    }
+
+    /// Drops refunded purchases from the allocation
+    pub fn remove_purchases(&mut self, purchases: &Purchases) -> Result<()> {
+        for (purchased, refunded) in self
+            .tokens_purchased_per_round
+            .iter_mut()
+            .zip(purchases.tokens_per_round)
+        {
+            *purchased = purchased
+                .checked_sub(refunded)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        self.total_tokens_purchased = self
+            .total_tokens_purchased
+            .checked_sub(purchases.total_tokens()?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.total_contributed_usd = self
+            .total_contributed_usd
+            .checked_sub(purchases.contributed_usd)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    /// Tokens unlocked and not yet claimed, `round_vesting` holds the schedule of every round
+    /// the user bought in
+    pub fn claimable(
+        &self,
+        round_vesting: &[Option<VestingSchedule>; MAX_STAGES],
+        tge_timestamp: i64,
+        now: i64,
+    ) -> Result<u64> {
+        let mut unlocked: u64 = 0;
+        for (purchased, vesting) in self.tokens_purchased_per_round.iter().zip(round_vesting) {
+            if *purchased == 0 {
+                continue;
+            }
+            let vesting = vesting.ok_or(ErrorCode::MissingRoundAccount)?;
+            unlocked = unlocked
+                .checked_add(vesting.unlocked_amount(tge_timestamp, *purchased, now)?)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+
+        unlocked
+            .checked_sub(self.claimed_tokens)
+            .ok_or(error!(ErrorCode::ArithmeticOverflow))
+    }
+}
+
+impl Purchases {
+    pub fn add(&mut self, contributed_usd: u64, fills: &[RoundFill]) -> Result<()> {
+        self.contributed_usd = self
+            .contributed_usd
+            .checked_add(contributed_usd)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        for fill in fills {
+            let tokens = &mut self.tokens_per_round[(fill.round_id - 1) as usize];
+            *tokens = tokens
+                .checked_add(fill.amount_tokens + fill.amount_referral_bonus_tokens)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn sub(&mut self, other: &Purchases) -> Result<()> {
+        self.contributed_usd = self
+            .contributed_usd
+            .checked_sub(other.contributed_usd)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        for (tokens, other_tokens) in self.tokens_per_round.iter_mut().zip(other.tokens_per_round) {
+            *tokens = tokens
+                .checked_sub(other_tokens)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn total_tokens(&self) -> Result<u64> {
+        self.tokens_per_round
+            .iter()
+            .try_fold(0u64, |total, tokens| total.checked_add(*tokens))
+            .ok_or(error!(ErrorCode::ArithmeticOverflow))
+    }
+}
+
+impl PresaleConfig {
+    pub fn role_mut(&mut self, role: Role) -> &mut Pubkey {
+        match role {
+            Role::Operator => &mut self.operator,
+            Role::Pauser => &mut self.pauser,
+            Role::Cosigner => &mut self.cosigner,
+        }
+    }
+
+    /// Sensitive changes go through queue_change while a timelock delay is set
+    pub fn is_timelocked(&self) -> bool {
+        self.timelock_delay > 0
+    }
+
+    /// The soft cap decides between refunds and withdrawals, so it is fixed once the final round
+    /// is over
+    pub fn is_soft_cap_locked(&self, final_round: &Round, now: i64) -> bool {
+        self.finalized || final_round.is_sold_out() || now > final_round.end_time
+    }
+
+    /// Takes refunded purchases out of the raise and the allocated supply
+    pub fn remove_purchases(&mut self, purchases: &Purchases) -> Result<()> {
+        self.total_raised_usd = self
+            .total_raised_usd
+            .checked_sub(purchases.contributed_usd)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.total_allocated_tokens = self
+            .total_allocated_tokens
+            .checked_sub(purchases.total_tokens()?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn is_payment_method_enabled(&self, payment_method: u8) -> bool {
+        self.enabled_payment_methods & payment_method != 0
+    }
+
+    /// Refunds open once the final round has ended without the soft cap being reached
+    pub fn refunds_available(&self, final_round: &Round, now: i64) -> bool {
+        self.escrow_enabled
+            && !self.finalized
+            && self.current_round == self.last_round
+            && final_round.round_id == self.last_round
+            && now > final_round.end_time
+            && self.total_raised_usd < self.soft_cap_usd
+    }
+}
+
+impl CreateRoundData {
+    /// Validates the round against the round scheduled right before it: rounds may not
+    /// overlap and prices never decrease
+    pub fn validate(&self, previous_end_time: i64, previous_price_usd: u64) -> Result<()> {
+        require!(self.token_price_usd > 0, ErrorCode::InvalidRoundConfig);
+        require!(self.token_supply > 0, ErrorCode::InvalidRoundConfig);
+        require!(
+            self.start_time < self.end_time,
+            ErrorCode::InvalidRoundConfig
+        );
+        require!(
+            self.start_time >= previous_end_time,
+            ErrorCode::InvalidRoundConfig
+        );
+        require!(
+            self.token_price_usd >= previous_price_usd,
+            ErrorCode::InvalidRoundConfig
+        );
+        require!(self.vesting.is_valid(), ErrorCode::InvalidVestingSchedule);
+        require!(
+            self.pricing_strategy.is_valid(self.token_price_usd),
+            ErrorCode::InvalidRoundConfig
+        );
+
+        Ok(())
+    }
+}
+
+impl OracleConfigData {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.max_staleness > 0, ErrorCode::InvalidOracleConfig);
+        // price - conf must stay positive for conservative pricing
+        require!(
+            self.max_price_conf_bps > 0 && (self.max_price_conf_bps as usize) < BASIS_POINTS,
+            ErrorCode::InvalidOracleConfig
+        );
+        require!(self.max_deviation_bps > 0, ErrorCode::InvalidOracleConfig);
+        if self.primary_source == OracleSource::Switchboard {
+            require_keys_neq!(
+                self.switchboard_feed,
+                Pubkey::default(),
+                ErrorCode::InvalidOracleConfig
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigChange {
+    /// Shortest delay the change waits regardless of `PresaleConfig.timelock_delay`. Buyers of an
+    /// escrowed sale get time to react before the soft cap deciding their refund moves.
+    pub fn min_delay(&self, escrow_enabled: bool) -> i64 {
+        match self {
+            ConfigChange::SoftCap { .. } if escrow_enabled => MIN_ESCROW_SOFT_CAP_DELAY,
+            _ => 0,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ConfigChange::Treasury { .. } | ConfigChange::SoftCap { .. } => Ok(()),
+            ConfigChange::OracleConfig { data } => data.validate(),
+            ConfigChange::AcceptedMint { data, .. } => data.validate(),
+            ConfigChange::TimelockDelay { timelock_delay } => {
+                require!(
+                    (0..=MAX_TIMELOCK_DELAY).contains(timelock_delay),
+                    ErrorCode::InvalidTimelockDelay
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+impl PendingChange {
+    pub fn new(
+        change_id: u64,
+        change: ConfigChange,
+        queued_at: i64,
+        timelock_delay: i64,
+        bump: u8,
+    ) -> Result<Self> {
+        let effective_at = queued_at
+            .checked_add(timelock_delay)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(Self {
+            change_id,
+            change,
+            queued_at,
+            effective_at,
+            bump,
+        })
+    }
+
+    pub fn is_effective(&self, now: i64) -> bool {
+        now >= self.effective_at
+    }
+}
+
+impl AcceptedMint {
+    /// Under the timelock only disabling the mint or picking up a treasury change applies
+    /// directly, anything else is queued
+    pub fn allows_direct_update(&self, data: &AcceptedMintData, timelocked: bool) -> bool {
+        !timelocked
+            || !data.enabled
+            || (self.enabled
+                && self.price_feed == data.price_feed
+                && self.stable == data.stable
+                && self.allow_permanent_delegate == data.allow_permanent_delegate)
+    }
+}
+
+impl AcceptedMintData {
+    pub fn validate(&self) -> Result<()> {
+        // Stablecoins are priced 1:1, everything else through its feed
+        require!(
+            self.stable != self.price_feed.is_some(),
+            ErrorCode::InvalidAcceptedMint
+        );
+
+        Ok(())
+    }
+}
+
+impl OracleConfig {
+    pub fn new(data: &OracleConfigData, bump: u8) -> Self {
+        Self {
+            feed_id: data.feed_id,
+            price_account: data.price_account,
+            max_staleness: data.max_staleness,
+            max_price_conf_bps: data.max_price_conf_bps,
+            conservative_pricing: data.conservative_pricing,
+            primary_source: data.primary_source,
+            switchboard_feed: data.switchboard_feed,
+            max_deviation_bps: data.max_deviation_bps,
+            bump,
+        }
+    }
+}
+
+impl PricingStrategy {
+    pub fn is_valid(&self, start_price_usd: u64) -> bool {
+        match *self {
+            PricingStrategy::Fixed => true,
+            PricingStrategy::TimeLinear { end_price_usd } => end_price_usd > 0,
+            PricingStrategy::TimeStepped {
+                end_price_usd,
+                steps,
+            } => end_price_usd > 0 && steps >= 2,
+            // Supply curves only ever rise
+            PricingStrategy::SupplyLinear {
+                start_supply,
+                end_supply,
+                end_price_usd,
+            } => end_supply > start_supply && end_price_usd >= start_price_usd,
+            PricingStrategy::SupplyPiecewise {
+                start_supply,
+                points,
+                point_count,
+            } => {
+                let point_count = point_count as usize;
+                if point_count == 0 || point_count > MAX_PRICE_POINTS {
+                    return false;
+                }
+
+                let mut previous = PricePoint {
+                    supply: start_supply,
+                    price_usd: start_price_usd,
+                };
+                for point in points[..point_count].iter() {
+                    if point.supply <= previous.supply || point.price_usd < previous.price_usd {
+                        return false;
+                    }
+                    previous = *point;
+                }
+                true
+            }
+        }
+    }
+}
+
+impl VestingSchedule {
+    pub fn is_valid(&self) -> bool {
+        self.tge_unlock_bps as usize <= BASIS_POINTS
+            && self.cliff_seconds >= 0
+            && self.vesting_duration >= 0
+    }
+
+    /// Amount of `total` unlocked at `now`: the TGE share at `tge_timestamp`, and the rest
+    /// linearly over `vesting_duration` once the cliff has passed. Nothing unlocks while the TGE
+    /// timestamp is unset.
+    pub fn unlocked_amount(&self, tge_timestamp: i64, total: u64, now: i64) -> Result<u64> {
+        if tge_timestamp == 0 || now < tge_timestamp {
+            return Ok(0);
+        }
+
+        let tge_unlocked = (total as u128)
+            .checked_mul(self.tge_unlock_bps as u128)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_div(BASIS_POINTS as u128)
+            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+
+        let vesting_start = tge_timestamp
+            .checked_add(self.cliff_seconds)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        if now < vesting_start {
+            return Ok(tge_unlocked);
+        }
+
+        let elapsed = now - vesting_start;
+        if elapsed >= self.vesting_duration {
+            return Ok(total);
+        }
+
+        let vested = ((total - tge_unlocked) as u128)
+            .checked_mul(elapsed as u128)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_div(self.vesting_duration as u128)
+            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+
+        Ok(tge_unlocked + vested)
+    }
+}
+
 impl Round {
-    pub fn is_active(&self) -> bool {
-        let now = Clock::get().unwrap().unix_timestamp;
+    pub fn new(round_id: u8, data: &CreateRoundData, bump: u8) -> Self {
+        Self {
+            round_id,
+            token_price_usd: data.token_price_usd,
+            start_time: data.start_time,
+            end_time: data.end_time,
+            vesting: data.vesting,
+            token_supply: data.token_supply,
+            tokens_sold: 0,
+            pricing_strategy: data.pricing_strategy,
+            bump,
+        }
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
         now >= self.start_time && now <= self.end_time
     }
+
+    pub fn remaining_supply(&self) -> u64 {
+        self.token_supply.saturating_sub(self.tokens_sold)
+    }
+
+    pub fn is_sold_out(&self) -> bool {
+        self.remaining_supply() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TGE: i64 = 1_000;
+    const TOTAL: u64 = 1_000_000;
+
+    fn vesting(tge_unlock_bps: u16) -> VestingSchedule {
+        VestingSchedule {
+            tge_unlock_bps,
+            cliff_seconds: 100,
+            vesting_duration: 1_000,
+        }
+    }
+
+    #[test]
+    fn nothing_unlocks_before_tge_or_while_unset() {
+        assert_eq!(
+            vesting(1_000).unlocked_amount(TGE, TOTAL, TGE - 1).unwrap(),
+            0
+        );
+        assert_eq!(
+            vesting(1_000).unlocked_amount(0, TOTAL, i64::MAX).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn tge_share_unlocks_at_tge_and_holds_until_cliff() {
+        let vesting = vesting(1_000);
+        assert_eq!(vesting.unlocked_amount(TGE, TOTAL, TGE).unwrap(), 100_000);
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 99).unwrap(),
+            100_000
+        );
+    }
+
+    #[test]
+    fn remainder_vests_linearly_after_cliff() {
+        let vesting = vesting(1_000);
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 100).unwrap(),
+            100_000
+        );
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 600).unwrap(),
+            550_000
+        );
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 1_099).unwrap(),
+            999_100
+        );
+    }
+
+    #[test]
+    fn everything_unlocks_after_full_duration() {
+        let vesting = vesting(1_000);
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 1_100).unwrap(),
+            TOTAL
+        );
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, i64::MAX).unwrap(),
+            TOTAL
+        );
+    }
+
+    #[test]
+    fn full_tge_unlock_releases_everything_at_tge() {
+        let vesting = vesting(BASIS_POINTS as u16);
+        assert_eq!(vesting.unlocked_amount(TGE, TOTAL, TGE).unwrap(), TOTAL);
+        assert_eq!(
+            vesting.unlocked_amount(TGE, TOTAL, TGE + 500).unwrap(),
+            TOTAL
+        );
+    }
+
+    const END: i64 = 5_000;
+
+    fn escrowed_sale() -> PresaleConfig {
+        PresaleConfig {
+            presale_id: 0,
+            authority: Pubkey::default(),
+            pending_authority: Pubkey::default(),
+            treasury: Pubkey::default(),
+            token_mint: Pubkey::default(),
+            current_round: 3,
+            last_round: 3,
+            finalized: false,
+            total_allocated_tokens: 0,
+            tge_timestamp: 0,
+            soft_cap_usd: 1_000_000,
+            total_raised_usd: 999_999,
+            escrow_enabled: true,
+            paused: false,
+            enabled_payment_methods: 0,
+            operator: Pubkey::default(),
+            pauser: Pubkey::default(),
+            cosigner: Pubkey::default(),
+            timelock_delay: 0,
+            next_change_id: 0,
+            bump: 0,
+        }
+    }
+
+    fn final_round() -> Round {
+        Round {
+            round_id: 3,
+            token_price_usd: 1,
+            start_time: 0,
+            end_time: END,
+            vesting: VestingSchedule::default(),
+            token_supply: 1,
+            tokens_sold: 0,
+            pricing_strategy: PricingStrategy::Fixed,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn refunds_open_after_final_round_ends_below_soft_cap() {
+        let config = escrowed_sale();
+        assert!(config.refunds_available(&final_round(), END + 1));
+        assert!(!config.refunds_available(&final_round(), END));
+    }
+
+    #[test]
+    fn refunds_stay_closed_at_or_above_soft_cap() {
+        let mut config = escrowed_sale();
+        config.total_raised_usd = config.soft_cap_usd;
+        assert!(!config.refunds_available(&final_round(), END + 1));
+    }
+
+    #[test]
+    fn refunds_require_escrow_and_an_unfinalized_sale() {
+        let mut config = escrowed_sale();
+        config.escrow_enabled = false;
+        assert!(!config.refunds_available(&final_round(), END + 1));
+
+        let mut config = escrowed_sale();
+        config.finalized = true;
+        assert!(!config.refunds_available(&final_round(), END + 1));
+    }
+
+    #[test]
+    fn refunds_require_the_last_round() {
+        let mut config = escrowed_sale();
+        config.current_round = 2;
+        assert!(!config.refunds_available(&final_round(), END + 1));
+
+        let mut round = final_round();
+        round.round_id = 2;
+        assert!(!escrowed_sale().refunds_available(&round, END + 1));
+    }
+
+    fn accepted_mint() -> AcceptedMint {
+        AcceptedMint {
+            mint: Pubkey::default(),
+            decimals: 6,
+            price_feed: None,
+            stable: true,
+            treasury_ata: Pubkey::default(),
+            enabled: true,
+            allow_permanent_delegate: false,
+            bump: 0,
+        }
+    }
+
+    fn pyth_feed() -> Option<PythFeed> {
+        Some(PythFeed {
+            feed_id: [1; 32],
+            price_account: Pubkey::default(),
+        })
+    }
+
+    #[test]
+    fn timelock_delay_change_is_bounded() {
+        let delay = |timelock_delay| ConfigChange::TimelockDelay { timelock_delay }.validate();
+        assert!(delay(0).is_ok());
+        assert!(delay(MAX_TIMELOCK_DELAY).is_ok());
+        assert_eq!(
+            delay(-1).unwrap_err(),
+            error!(ErrorCode::InvalidTimelockDelay)
+        );
+        assert_eq!(
+            delay(MAX_TIMELOCK_DELAY + 1).unwrap_err(),
+            error!(ErrorCode::InvalidTimelockDelay)
+        );
+    }
+
+    #[test]
+    fn queued_accepted_mint_change_is_validated() {
+        let change = ConfigChange::AcceptedMint {
+            mint: Pubkey::default(),
+            data: AcceptedMintData {
+                price_feed: pyth_feed(),
+                stable: true,
+                enabled: true,
+                allow_permanent_delegate: false,
+            },
+        };
+        assert_eq!(
+            change.validate().unwrap_err(),
+            error!(ErrorCode::InvalidAcceptedMint)
+        );
+    }
+
+    #[test]
+    fn pending_change_takes_effect_after_delay() {
+        let change = ConfigChange::SoftCap { soft_cap_usd: 1 };
+        let pending_change = PendingChange::new(0, change, 1_000, 3_600, 0).unwrap();
+        assert_eq!(pending_change.effective_at, 4_600);
+        assert!(!pending_change.is_effective(4_599));
+        assert!(pending_change.is_effective(4_600));
+
+        // Without a delay the change can be executed right away
+        let pending_change = PendingChange::new(1, change, 1_000, 0, 0).unwrap();
+        assert!(pending_change.is_effective(1_000));
+
+        assert!(PendingChange::new(2, change, i64::MAX, 1, 0).is_err());
+    }
+
+    #[test]
+    fn timelock_only_lets_accepted_mints_be_disabled_directly() {
+        let accepted_mint = accepted_mint();
+        let enable_with_feed = AcceptedMintData {
+            price_feed: pyth_feed(),
+            stable: false,
+            enabled: true,
+            allow_permanent_delegate: false,
+        };
+        let unchanged = AcceptedMintData {
+            price_feed: None,
+            stable: true,
+            enabled: true,
+            allow_permanent_delegate: false,
+        };
+        let disable = AcceptedMintData {
+            enabled: false,
+            ..enable_with_feed
+        };
+
+        assert!(accepted_mint.allows_direct_update(&enable_with_feed, false));
+        assert!(!accepted_mint.allows_direct_update(&enable_with_feed, true));
+        assert!(accepted_mint.allows_direct_update(&unchanged, true));
+        assert!(accepted_mint.allows_direct_update(&disable, true));
+
+        let disabled_mint = AcceptedMint {
+            enabled: false,
+            ..accepted_mint
+        };
+        assert!(!disabled_mint.allows_direct_update(&unchanged, true));
+
+        let mut config = escrowed_sale();
+        assert!(!config.is_timelocked());
+        config.timelock_delay = 1;
+        assert!(config.is_timelocked());
+    }
+
+    fn round_fill(round_id: u8, amount_tokens: u64) -> RoundFill {
+        RoundFill {
+            round_id,
+            amount_tokens,
+            amount_referral_bonus_tokens: amount_tokens / 10,
+            token_price_usd: 0,
+        }
+    }
+
+    /// Records a purchase the way the buy instructions do, returning its share
+    fn buy(user_contribution: &mut UserContribution, usd: u64, fills: &[RoundFill]) -> Purchases {
+        let mut purchases = Purchases::default();
+        purchases.add(usd, fills).unwrap();
+        user_contribution.total_contributed_usd += usd;
+        user_contribution.total_tokens_purchased += purchases.total_tokens().unwrap();
+        for fill in fills {
+            user_contribution.tokens_purchased_per_round[(fill.round_id - 1) as usize] +=
+                fill.amount_tokens + fill.amount_referral_bonus_tokens;
+        }
+        purchases
+    }
+
+    #[test]
+    fn nothing_is_claimable_after_token_refund() {
+        let round_vesting = [Some(vesting(BASIS_POINTS as u16)); MAX_STAGES];
+        let mut config = escrowed_sale();
+        let mut user_contribution = UserContribution::new(Pubkey::default(), 0);
+
+        let token_purchases = buy(
+            &mut user_contribution,
+            500_000,
+            &[round_fill(1, 1_000), round_fill(2, 2_000)],
+        );
+        user_contribution.token_purchases = token_purchases;
+        config.total_raised_usd = 500_000;
+        config.total_allocated_tokens = 3_300;
+
+        // refund_token
+        user_contribution
+            .remove_purchases(&token_purchases)
+            .unwrap();
+        user_contribution
+            .token_purchases
+            .sub(&token_purchases)
+            .unwrap();
+        config.remove_purchases(&token_purchases).unwrap();
+
+        assert_eq!(user_contribution.total_contributed_usd, 0);
+        assert_eq!(user_contribution.total_tokens_purchased, 0);
+        assert_eq!(config.total_raised_usd, 0);
+        assert_eq!(config.total_allocated_tokens, 0);
+        assert_eq!(
+            user_contribution
+                .claimable(&round_vesting, TGE, TGE)
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn token_refund_keeps_direct_purchases() {
+        let round_vesting = [Some(vesting(BASIS_POINTS as u16)); MAX_STAGES];
+        let mut user_contribution = UserContribution::new(Pubkey::default(), 0);
+
+        buy(&mut user_contribution, 100_000, &[round_fill(1, 1_000)]);
+        let token_purchases = buy(&mut user_contribution, 200_000, &[round_fill(1, 2_000)]);
+        user_contribution.token_purchases = token_purchases;
+
+        let direct_purchases = user_contribution.direct_purchases().unwrap();
+        assert_eq!(direct_purchases.contributed_usd, 100_000);
+        assert_eq!(direct_purchases.total_tokens().unwrap(), 1_100);
+
+        user_contribution
+            .remove_purchases(&token_purchases)
+            .unwrap();
+        user_contribution
+            .token_purchases
+            .sub(&token_purchases)
+            .unwrap();
+
+        assert_eq!(user_contribution.total_contributed_usd, 100_000);
+        assert_eq!(
+            user_contribution
+                .claimable(&round_vesting, TGE, TGE)
+                .unwrap(),
+            1_100
+        );
+    }
+
+    #[test]
+    fn direct_refund_keeps_token_purchases_and_quote_nonce() {
+        let mut config = escrowed_sale();
+        let mut user_contribution = UserContribution::new(Pubkey::default(), 0);
+        user_contribution.quote_nonce = 7;
+
+        buy(&mut user_contribution, 100_000, &[round_fill(1, 1_000)]);
+        let token_purchases = buy(&mut user_contribution, 200_000, &[round_fill(2, 2_000)]);
+        user_contribution.token_purchases = token_purchases;
+        config.total_raised_usd = 300_000;
+        config.total_allocated_tokens = 3_300;
+
+        // refund
+        let direct_purchases = user_contribution.direct_purchases().unwrap();
+        user_contribution
+            .remove_purchases(&direct_purchases)
+            .unwrap();
+        config.remove_purchases(&direct_purchases).unwrap();
+
+        assert_eq!(config.total_raised_usd, 200_000);
+        assert_eq!(config.total_allocated_tokens, 2_200);
+        assert_eq!(user_contribution.total_contributed_usd, 200_000);
+        assert_eq!(user_contribution.tokens_purchased_per_round[0], 0);
+        assert_eq!(user_contribution.tokens_purchased_per_round[1], 2_200);
+        assert_eq!(user_contribution.quote_nonce, 7);
+
+        // refund_token afterwards empties the allocation
+        user_contribution
+            .remove_purchases(&token_purchases)
+            .unwrap();
+        config.remove_purchases(&token_purchases).unwrap();
+        assert_eq!(user_contribution.total_tokens_purchased, 0);
+        assert_eq!(config.total_allocated_tokens, 0);
+    }
+
+    #[test]
+    fn soft_cap_locks_once_the_final_round_is_over() {
+        let mut config = escrowed_sale();
+        assert!(!config.is_soft_cap_locked(&final_round(), END));
+        assert!(config.is_soft_cap_locked(&final_round(), END + 1));
+
+        let mut sold_out = final_round();
+        sold_out.tokens_sold = sold_out.token_supply;
+        assert!(config.is_soft_cap_locked(&sold_out, END));
+
+        config.finalized = true;
+        assert!(config.is_soft_cap_locked(&final_round(), END));
+    }
+
+    #[test]
+    fn escrowed_soft_cap_change_waits_the_minimum_delay() {
+        let soft_cap = ConfigChange::SoftCap { soft_cap_usd: 1 };
+        assert_eq!(soft_cap.min_delay(true), MIN_ESCROW_SOFT_CAP_DELAY);
+        assert_eq!(soft_cap.min_delay(false), 0);
+        assert_eq!(
+            ConfigChange::TimelockDelay { timelock_delay: 0 }.min_delay(true),
+            0
+        );
+    }
 }